@@ -0,0 +1,168 @@
+//! Measures how long `make_action` takes per agent in synthetic worlds of different sizes. The
+//! worlds are fed through the exported functions, like the host does.
+//!
+//! Run with `cargo bench`, a filter argument only runs the scenarios whose name contains it.
+
+use std::time::{Duration, Instant};
+
+use quick_start_simple::{
+    Context, clear_world_state, init_agent, make_action, set_config_parameter, update_ship,
+    update_shot,
+};
+
+/// Ticks that are played before measuring, so that the estimators and buffers settle.
+const WARMUP_TICKS: u32 = 20;
+
+/// Ticks that are measured per scenario.
+const MEASURED_TICKS: u32 = 30;
+
+struct Scenario {
+    name: &'static str,
+    ships: u32,
+    shots: u32,
+}
+
+const SCENARIOS: [Scenario; 6] = [
+    Scenario {
+        name: "duel",
+        ships: 2,
+        shots: 2,
+    },
+    Scenario {
+        name: "small",
+        ships: 10,
+        shots: 20,
+    },
+    Scenario {
+        name: "medium",
+        ships: 50,
+        shots: 200,
+    },
+    Scenario {
+        name: "large",
+        ships: 200,
+        shots: 1000,
+    },
+    Scenario {
+        name: "crowded",
+        ships: 500,
+        shots: 2000,
+    },
+    Scenario {
+        name: "shot storm",
+        ships: 500,
+        shots: 5000,
+    },
+];
+
+/// Small deterministic random number generator, so that every run measures the same worlds.
+struct Random(u32);
+
+impl Random {
+    fn next(&mut self) -> f32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        (self.0 >> 8) as f32 / (1u32 << 24) as f32
+    }
+}
+
+/// Start position, heading and turn of one ship.
+struct Track {
+    pos_x: f32,
+    pos_y: f32,
+    heading: f32,
+    turn: f32,
+}
+
+/// Feeds the world of `tick` to the agent. Ships fly with constant speed on circles, the shots
+/// fly straight and are replaced once they expire.
+fn update_world(ctx: &mut Context, tracks: &[Track], shots: u32, tick: u32) {
+    clear_world_state(ctx);
+    for (agent_id, track) in tracks.iter().enumerate() {
+        let heading = track.heading + track.turn * tick as f32;
+        let radians = (90.0 - heading).to_radians();
+        let pos_x = (track.pos_x + 0.002 * tick as f32 * radians.cos()).rem_euclid(1.0);
+        let pos_y = (track.pos_y + 0.002 * tick as f32 * radians.sin()).rem_euclid(1.0);
+        update_ship(
+            ctx,
+            agent_id as u32,
+            3,
+            pos_x,
+            pos_y,
+            heading.rem_euclid(360.0),
+        );
+    }
+    for shot in 0..shots {
+        // shots belong to the ships round robin, the host would only allow one per agent
+        let agent_id = shot % tracks.len() as u32;
+        let track = &tracks[agent_id as usize];
+        let age = (tick + shot) % 60;
+        let radians = (90.0 - track.heading).to_radians();
+        let pos_x = (track.pos_x + 0.01 * age as f32 * radians.cos()).rem_euclid(1.0);
+        let pos_y = (track.pos_y + 0.01 * age as f32 * radians.sin()).rem_euclid(1.0);
+        update_shot(ctx, agent_id, 60 - age as i32, pos_x, pos_y, track.heading);
+    }
+}
+
+/// Runs one scenario and returns the duration of every `make_action` call.
+fn measure(scenario: &Scenario) -> Vec<Duration> {
+    let mut ctx = init_agent(scenario.ships, 1, 7);
+    for (param, value) in [(0, 10.0), (1, 0.005), (2, 0.02), (3, 0.01), (4, 60.0)] {
+        set_config_parameter(&mut ctx, param, value);
+    }
+    let mut random = Random(0x9e37_79b9);
+    let tracks: Vec<Track> = (0..scenario.ships)
+        .map(|_| Track {
+            pos_x: random.next(),
+            pos_y: random.next(),
+            heading: random.next() * 360.0,
+            turn: random.next() * 4.0 - 2.0,
+        })
+        .collect();
+    // the first half of the ships is ours
+    let own_agents = (scenario.ships / 2).max(1);
+
+    let mut durations = Vec::new();
+    for tick in 0..WARMUP_TICKS + MEASURED_TICKS {
+        update_world(&mut ctx, &tracks, scenario.shots, tick);
+        for agent_id in 0..own_agents {
+            let start = Instant::now();
+            std::hint::black_box(make_action(&mut ctx, agent_id, tick));
+            if tick >= WARMUP_TICKS {
+                durations.push(start.elapsed());
+            }
+        }
+    }
+    durations
+}
+
+fn main() {
+    // cargo passes `--bench` to benchmarks without the default harness
+    let filter = std::env::args().skip(1).find(|arg| !arg.starts_with("--"));
+    println!(
+        "{:<12} {:>6} {:>6} {:>7} {:>10} {:>10} {:>10} {:>10}",
+        "scenario", "ships", "shots", "calls", "mean", "median", "p99", "max"
+    );
+    for scenario in SCENARIOS.iter().filter(|scenario| {
+        filter
+            .as_ref()
+            .is_none_or(|filter| scenario.name.contains(filter))
+    }) {
+        let mut durations = measure(scenario);
+        durations.sort_unstable();
+        let mean = durations.iter().sum::<Duration>() / durations.len() as u32;
+        let percentile = |share: f32| durations[((durations.len() - 1) as f32 * share) as usize];
+        println!(
+            "{:<12} {:>6} {:>6} {:>7} {:>10.1?} {:>10.1?} {:>10.1?} {:>10.1?}",
+            scenario.name,
+            scenario.ships,
+            scenario.shots,
+            durations.len(),
+            mean,
+            percentile(0.5),
+            percentile(0.99),
+            percentile(1.0),
+        );
+    }
+}
@@ -0,0 +1,306 @@
+//! Generates the bindings of `scubywasm_agent.h` into `OUT_DIR`:
+//!
+//! - `bindings.rs` with a type and constants for every enum of the header, included by
+//!   `src/bindings.rs`
+//! - `exports.rs` with one function pointer per prototype of the header, included by `src/lib.rs`
+//!   so that the build fails when one of our exports does not match its prototype
+//! - `wasm_exports.rs` with the name and the wasm signature of every prototype, included by the
+//!   `wasm-audit` tool that checks the built module against the header
+//!
+//! The header is read as C, the `__cplusplus` parts are skipped. Declarations this script does
+//! not understand fail the build instead of being left out of the bindings.
+
+use std::{env, fmt::Write, fs, path::Path};
+
+const HEADER: &str = "scubywasm_agent.h";
+
+struct Enum {
+    name: String,
+    variants: Vec<(String, u32)>,
+}
+
+struct Prototype {
+    name: String,
+    return_type: String,
+    parameters: Vec<String>,
+}
+
+#[derive(Default)]
+struct Header {
+    enums: Vec<Enum>,
+    /// structs that are only declared, the agent defines them
+    opaque_structs: Vec<String>,
+    prototypes: Vec<Prototype>,
+}
+
+fn main() {
+    println!("cargo::rerun-if-changed={HEADER}");
+    let text = fs::read_to_string(HEADER).unwrap_or_else(|error| panic!("{HEADER}: {error}"));
+    let header = parse(&text).unwrap_or_else(|error| panic!("{HEADER}: {error}"));
+
+    let out_dir = env::var("OUT_DIR").expect("cargo sets OUT_DIR");
+    let out_dir = Path::new(&out_dir);
+    fs::write(out_dir.join("bindings.rs"), bindings(&header)).expect("OUT_DIR is writable");
+    let exports = exports(&header).unwrap_or_else(|error| panic!("{HEADER}: {error}"));
+    fs::write(out_dir.join("exports.rs"), exports).expect("OUT_DIR is writable");
+    let wasm_exports = wasm_exports(&header).unwrap_or_else(|error| panic!("{HEADER}: {error}"));
+    fs::write(out_dir.join("wasm_exports.rs"), wasm_exports).expect("OUT_DIR is writable");
+}
+
+/// Parses the declarations of the header.
+fn parse(text: &str) -> Result<Header, String> {
+    let mut header = Header::default();
+    for declaration in without_comments(&c_lines(text)).split(';') {
+        let declaration = declaration.split_whitespace().collect::<Vec<_>>().join(" ");
+        if declaration.is_empty() {
+            continue;
+        }
+        if let Some(enumeration) = declaration.strip_prefix("enum ") {
+            header.enums.push(parse_enum(enumeration)?);
+        } else if declaration.contains('(') {
+            let prototype = parse_prototype(&declaration)?;
+            header.prototypes.push(prototype);
+        } else if let Some(name) = declaration.strip_prefix("struct ")
+            && is_identifier(name)
+        {
+            header.opaque_structs.push(name.to_string());
+        } else {
+            return Err(format!("unsupported declaration `{declaration}`"));
+        }
+    }
+    if header.enums.is_empty() || header.prototypes.is_empty() {
+        return Err("found no enums or no prototypes".into());
+    }
+    Ok(header)
+}
+
+/// The lines of the header a C compiler sees, without preprocessor directives.
+fn c_lines(text: &str) -> String {
+    let mut lines = String::new();
+    // nesting of the conditionals since the first skipped one, 0 when not skipping
+    let mut skipping: usize = 0;
+    for line in text.lines() {
+        let directive = line.trim_start().strip_prefix('#').map(str::trim_start);
+        match directive {
+            Some(directive)
+                if directive.starts_with("if")
+                    && (skipping > 0 || directive == "ifdef __cplusplus") =>
+            {
+                skipping += 1;
+            }
+            Some(directive) if directive.starts_with("endif") => {
+                skipping = skipping.saturating_sub(1);
+            }
+            Some(_) => {}
+            None if skipping == 0 => {
+                lines.push_str(line);
+                lines.push('\n');
+            }
+            None => {}
+        }
+    }
+    lines
+}
+
+fn without_comments(text: &str) -> String {
+    let mut result = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("/*").into_iter().chain(rest.find("//")).min() {
+        result.push_str(&rest[..start]);
+        let end = if rest[start..].starts_with("/*") {
+            rest[start..].find("*/").map(|end| start + end + 2)
+        } else {
+            rest[start..].find('\n').map(|end| start + end)
+        };
+        rest = &rest[end.unwrap_or(rest.len())..];
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Parses `Name : unsigned int { VARIANT = 0U, ... }`.
+fn parse_enum(declaration: &str) -> Result<Enum, String> {
+    let (head, body) = declaration
+        .split_once('{')
+        .ok_or_else(|| format!("enum without variants `{declaration}`"))?;
+    let name = head.split(':').next().unwrap_or_default().trim();
+    let underlying = head
+        .split_once(':')
+        .map(|(_, underlying)| underlying.trim());
+    if !is_identifier(name) || underlying.is_some_and(|underlying| underlying != "unsigned int") {
+        return Err(format!("unsupported enum `{head}`"));
+    }
+    let body = body
+        .trim()
+        .strip_suffix('}')
+        .ok_or_else(|| format!("enum {name} is not closed"))?;
+    let mut variants = Vec::new();
+    for variant in body.split(',').map(str::trim).filter(|v| !v.is_empty()) {
+        let (variant, value) = variant
+            .split_once('=')
+            .ok_or_else(|| format!("variant `{variant}` of {name} has no explicit value"))?;
+        let value = value.trim().trim_end_matches(['U', 'u']);
+        let value = value
+            .parse()
+            .map_err(|_| format!("value `{value}` of {name} is not an unsigned integer"))?;
+        variants.push((variant.trim().to_string(), value));
+    }
+    Ok(Enum {
+        name: name.to_string(),
+        variants,
+    })
+}
+
+/// Parses `return_type name(type name, ...)`, only the types are kept.
+fn parse_prototype(declaration: &str) -> Result<Prototype, String> {
+    let declaration = declaration.replace('*', " * ").replace('(', " ( ");
+    let (head, parameters) = declaration
+        .split_once('(')
+        .ok_or_else(|| format!("unsupported prototype `{declaration}`"))?;
+    let (return_type, name) = split_name(head)?;
+    let parameters = parameters
+        .trim()
+        .strip_suffix(')')
+        .ok_or_else(|| format!("prototype of {name} is not closed"))?
+        .trim();
+    let parameters = if parameters == "void" || parameters.is_empty() {
+        Vec::new()
+    } else {
+        parameters
+            .split(',')
+            .map(|parameter| split_name(parameter).map(|(parameter_type, _)| parameter_type))
+            .collect::<Result<_, _>>()?
+    };
+    Ok(Prototype {
+        name,
+        return_type,
+        parameters,
+    })
+}
+
+/// Splits `type name` into the type and the name.
+fn split_name(declaration: &str) -> Result<(String, String), String> {
+    let words: Vec<_> = declaration.split_whitespace().collect();
+    match words.split_last() {
+        Some((name, declared_type)) if is_identifier(name) && !declared_type.is_empty() => {
+            Ok((declared_type.join(" "), name.to_string()))
+        }
+        _ => Err(format!("expected a type and a name, got `{declaration}`")),
+    }
+}
+
+fn is_identifier(text: &str) -> bool {
+    !text.is_empty()
+        && !text.starts_with(|c: char| c.is_ascii_digit())
+        && text.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Rust type of the C type `c_type` of a parameter, or of a return value when `returned`.
+///
+/// The agent owns the opaque structs: it hands them out as `Box` and receives them back as
+/// `&mut`, both have the ABI of a pointer.
+fn rust_type(header: &Header, c_type: &str, returned: bool) -> Result<String, String> {
+    let rust_type = match c_type {
+        "uint32_t" => "u32".to_string(),
+        "int32_t" => "i32".to_string(),
+        "float" => "f32".to_string(),
+        "void" if returned => "()".to_string(),
+        _ => {
+            if let Some(name) = c_type.strip_prefix("enum ")
+                && header
+                    .enums
+                    .iter()
+                    .any(|enumeration| enumeration.name == name)
+            {
+                format!("bindings::{name}")
+            } else if let Some(name) = c_type
+                .strip_prefix("struct ")
+                .and_then(|pointer| pointer.strip_suffix(" *"))
+                && header.opaque_structs.iter().any(|opaque| opaque == name)
+            {
+                if returned {
+                    format!("Box<{name}>")
+                } else {
+                    format!("&mut {name}")
+                }
+            } else {
+                return Err(format!("unsupported type `{c_type}`"));
+            }
+        }
+    };
+    Ok(rust_type)
+}
+
+fn bindings(header: &Header) -> String {
+    let mut code = format!("// generated by build.rs from {HEADER}, do not edit\n");
+    for enumeration in &header.enums {
+        let name = &enumeration.name;
+        let _ = writeln!(code, "\npub type {name} = core::ffi::c_uint;");
+        for (variant, value) in &enumeration.variants {
+            let _ = writeln!(code, "pub const {name}_{variant}: {name} = {value};");
+        }
+    }
+    code
+}
+
+fn exports(header: &Header) -> Result<String, String> {
+    let mut code = format!("// generated by build.rs from {HEADER}, do not edit\n");
+    for prototype in &header.prototypes {
+        let in_prototype = |error: String| format!("{}: {error}", prototype.name);
+        let parameters = prototype
+            .parameters
+            .iter()
+            .map(|parameter| rust_type(header, parameter, false))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(in_prototype)?;
+        let return_type = rust_type(header, &prototype.return_type, true).map_err(in_prototype)?;
+        let returned = if return_type == "()" {
+            String::new()
+        } else {
+            format!(" -> {return_type}")
+        };
+        let _ = writeln!(
+            code,
+            "const _: extern \"C\" fn({}){returned} = {};",
+            parameters.join(", "),
+            prototype.name
+        );
+    }
+    Ok(code)
+}
+
+/// Wasm value type of the C type `c_type`, `None` for `void`.
+fn wasm_type(header: &Header, c_type: &str) -> Result<Option<&'static str>, String> {
+    // enums are unsigned ints and the opaque structs are passed as pointers into the memory
+    match rust_type(header, c_type, true)?.as_str() {
+        "()" => Ok(None),
+        "f32" => Ok(Some("f32")),
+        _ => Ok(Some("i32")),
+    }
+}
+
+fn wasm_exports(header: &Header) -> Result<String, String> {
+    let mut code = format!("// generated by build.rs from {HEADER}, do not edit\n&[\n");
+    for prototype in &header.prototypes {
+        let in_prototype = |error: String| format!("{}: {error}", prototype.name);
+        let parameters = prototype
+            .parameters
+            .iter()
+            .map(|parameter| wasm_type(header, parameter))
+            .collect::<Result<Option<Vec<_>>, _>>()
+            .map_err(in_prototype)?
+            .ok_or_else(|| in_prototype("`void` parameter".into()))?;
+        let returned = match wasm_type(header, &prototype.return_type).map_err(in_prototype)? {
+            Some(return_type) => format!(" -> {return_type}"),
+            None => String::new(),
+        };
+        let _ = writeln!(
+            code,
+            "    (\"{}\", \"({}){returned}\"),",
+            prototype.name,
+            parameters.join(", ")
+        );
+    }
+    code.push_str("]\n");
+    Ok(code)
+}
@@ -27,7 +27,11 @@
         # Common arguments can be set here to avoid repeating them later
         # Note: changes here will rebuild all dependency crates
         commonArgs = {
-          src = craneLib.cleanCargoSource ./.;
+          # keep the header as well, the build script generates the bindings from it
+          src = pkgs.lib.cleanSourceWith {
+            src = ./.;
+            filter = path: type: (pkgs.lib.hasSuffix ".h" path) || (craneLib.filterCargoSources path type);
+          };
           strictDeps = true;
 
           buildInputs = [
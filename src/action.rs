@@ -0,0 +1,266 @@
+use core::fmt::{Display, Formatter};
+
+use crate::{Action, TurnDirection, bindings};
+
+/// Bits of all flags the host knows.
+const KNOWN_FLAGS: u32 = bindings::ActionFlags_ACTION_THRUST
+    | bindings::ActionFlags_ACTION_TURN_LEFT
+    | bindings::ActionFlags_ACTION_TURN_RIGHT
+    | bindings::ActionFlags_ACTION_FIRE;
+
+/// Why a requested flag was dropped from an action.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DropReason {
+    /// the bit is not an action flag of the host
+    UnknownFlag,
+    /// turning left and right at once cancels out
+    OppositeTurn,
+    /// our shot is still in flight, the host would ignore the shot
+    NoShotAvailable,
+    /// turning would distort the shot that is fired
+    TurnWhileFiring,
+}
+
+/// Flag that was requested but is not part of the resolved action.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Dropped {
+    pub flag: u32,
+    pub reason: DropReason,
+}
+
+/// Collects the flags a strategy requests and resolves conflicts between them, it is the only
+/// place where the bitmask for the host is put together.
+///
+/// Conflicts are resolved in this order: unknown bits are dropped, opposite turns drop each
+/// other, firing is dropped while no shot is available and turning is dropped while firing.
+/// Thrust never conflicts.
+#[derive(Clone, Copy)]
+pub struct ActionBuilder {
+    requested: u32,
+    shot_available: bool,
+}
+
+/// Action after conflict resolution, with the flags that were dropped on the way.
+#[derive(Clone, Copy)]
+pub struct ResolvedAction {
+    bits: u32,
+    /// at most the two turns, fire and the unknown bits can be dropped
+    dropped: [Option<Dropped>; 4],
+}
+
+impl Default for ActionBuilder {
+    fn default() -> Self {
+        Self {
+            requested: bindings::ActionFlags_ACTION_NONE,
+            shot_available: true,
+        }
+    }
+}
+
+impl ActionBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Requests all flags of `action`.
+    pub fn request(mut self, action: Action) -> Self {
+        if action.enable_thrusters {
+            self = self.thrust();
+        }
+        if let Some(direction) = action.turn_direction {
+            self = self.turn(direction);
+        }
+        if action.fire {
+            self = self.fire();
+        }
+        self
+    }
+
+    /// Requests the raw flags in `bits`, like the ones simulated by the planner.
+    pub fn request_bits(mut self, bits: u32) -> Self {
+        self.requested |= bits;
+        self
+    }
+
+    pub fn thrust(self) -> Self {
+        self.request_bits(bindings::ActionFlags_ACTION_THRUST)
+    }
+
+    pub fn turn(self, direction: TurnDirection) -> Self {
+        self.request_bits(direction.into())
+    }
+
+    pub fn fire(self) -> Self {
+        self.request_bits(bindings::ActionFlags_ACTION_FIRE)
+    }
+
+    /// Whether our agent can fire, assumed when not set.
+    pub fn shot_available(mut self, shot_available: bool) -> Self {
+        self.shot_available = shot_available;
+        self
+    }
+
+    /// Resolves the conflicts between the requested flags.
+    pub fn build(self) -> ResolvedAction {
+        let mut resolved = ResolvedAction {
+            bits: self.requested,
+            dropped: [None; 4],
+        };
+        let unknown = resolved.bits & !KNOWN_FLAGS;
+        if unknown != 0 {
+            resolved.drop(unknown, DropReason::UnknownFlag);
+        }
+        let turns =
+            bindings::ActionFlags_ACTION_TURN_LEFT | bindings::ActionFlags_ACTION_TURN_RIGHT;
+        if resolved.bits & turns == turns {
+            resolved.drop(
+                bindings::ActionFlags_ACTION_TURN_LEFT,
+                DropReason::OppositeTurn,
+            );
+            resolved.drop(
+                bindings::ActionFlags_ACTION_TURN_RIGHT,
+                DropReason::OppositeTurn,
+            );
+        }
+        if resolved.bits & bindings::ActionFlags_ACTION_FIRE != 0 {
+            if !self.shot_available {
+                resolved.drop(
+                    bindings::ActionFlags_ACTION_FIRE,
+                    DropReason::NoShotAvailable,
+                );
+            } else if resolved.bits & turns != 0 {
+                resolved.drop(resolved.bits & turns, DropReason::TurnWhileFiring);
+            }
+        }
+        resolved
+    }
+}
+
+impl ResolvedAction {
+    /// Bitmask of `ActionFlags` that is sent to the host.
+    pub fn bits(&self) -> u32 {
+        self.bits
+    }
+
+    /// Flags that were requested but dropped.
+    pub fn dropped(&self) -> impl Iterator<Item = Dropped> + '_ {
+        self.dropped.iter().flatten().copied()
+    }
+
+    /// Whether the requested flags go together. Dropping fire while our shot is in flight does
+    /// not count, the host would ignore the shot anyway.
+    pub fn valid(&self) -> bool {
+        self.dropped()
+            .all(|dropped| dropped.reason == DropReason::NoShotAvailable)
+    }
+
+    fn drop(&mut self, flag: u32, reason: DropReason) {
+        self.bits &= !flag;
+        if let Some(slot) = self.dropped.iter_mut().find(|slot| slot.is_none()) {
+            *slot = Some(Dropped { flag, reason });
+        }
+    }
+}
+
+impl Display for DropReason {
+    fn fmt(&self, formatter: &mut Formatter) -> core::fmt::Result {
+        formatter.write_str(match self {
+            Self::UnknownFlag => "unknown flag",
+            Self::OppositeTurn => "opposite turn",
+            Self::NoShotAvailable => "no shot available",
+            Self::TurnWhileFiring => "turning while firing",
+        })
+    }
+}
+
+impl Display for Dropped {
+    fn fmt(&self, formatter: &mut Formatter) -> core::fmt::Result {
+        write!(formatter, "{:#b} ({})", self.flag, self.reason)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const THRUST: u32 = bindings::ActionFlags_ACTION_THRUST;
+    const LEFT: u32 = bindings::ActionFlags_ACTION_TURN_LEFT;
+    const RIGHT: u32 = bindings::ActionFlags_ACTION_TURN_RIGHT;
+    const FIRE: u32 = bindings::ActionFlags_ACTION_FIRE;
+
+    fn reasons(resolved: &ResolvedAction) -> Vec<(u32, DropReason)> {
+        resolved
+            .dropped()
+            .map(|dropped| (dropped.flag, dropped.reason))
+            .collect()
+    }
+
+    #[test]
+    fn compatible_flags_are_kept() {
+        let resolved = ActionBuilder::new()
+            .thrust()
+            .turn(TurnDirection::Left)
+            .build();
+        assert_eq!(resolved.bits(), THRUST | LEFT);
+        assert!(resolved.valid());
+        assert_eq!(resolved.dropped().count(), 0);
+    }
+
+    #[test]
+    fn unknown_bits_are_dropped() {
+        let resolved = ActionBuilder::new().request_bits(THRUST | 0x30).build();
+        assert_eq!(resolved.bits(), THRUST);
+        assert_eq!(reasons(&resolved), [(0x30, DropReason::UnknownFlag)]);
+        assert!(!resolved.valid());
+    }
+
+    #[test]
+    fn opposite_turns_drop_each_other() {
+        let resolved = ActionBuilder::new()
+            .request_bits(LEFT | RIGHT | FIRE)
+            .build();
+        // without the turns nothing distorts the shot anymore
+        assert_eq!(resolved.bits(), FIRE);
+        assert_eq!(
+            reasons(&resolved),
+            [
+                (LEFT, DropReason::OppositeTurn),
+                (RIGHT, DropReason::OppositeTurn)
+            ]
+        );
+    }
+
+    #[test]
+    fn turning_is_dropped_while_firing() {
+        let resolved = ActionBuilder::new().request_bits(RIGHT | FIRE).build();
+        assert_eq!(resolved.bits(), FIRE);
+        assert_eq!(reasons(&resolved), [(RIGHT, DropReason::TurnWhileFiring)]);
+        assert!(!resolved.valid());
+    }
+
+    #[test]
+    fn firing_without_a_shot_keeps_the_turn() {
+        let resolved = ActionBuilder::new()
+            .request_bits(LEFT | FIRE)
+            .shot_available(false)
+            .build();
+        assert_eq!(resolved.bits(), LEFT);
+        assert_eq!(reasons(&resolved), [(FIRE, DropReason::NoShotAvailable)]);
+        // the host would ignore the shot anyway
+        assert!(resolved.valid());
+    }
+
+    #[test]
+    fn actions_are_requested_flag_by_flag() {
+        let action = Action {
+            turn_direction: Some(TurnDirection::Right),
+            enable_thrusters: true,
+            fire: false,
+        };
+        assert_eq!(
+            ActionBuilder::new().request(action).build().bits(),
+            THRUST | RIGHT
+        );
+        assert_eq!(u32::from(action), THRUST | RIGHT);
+    }
+}
@@ -0,0 +1,252 @@
+//! Bump allocator of the build without std on wasm32.
+//!
+//! The agent does not allocate once its buffers grew to the size of the match, so memory is
+//! handed out by moving a pointer through pages requested from the host and freed memory is
+//! only reused when it was the last allocation. This is much smaller than a general purpose
+//! allocator.
+
+#[cfg(target_arch = "wasm32")]
+use core::arch::wasm32;
+use core::{
+    alloc::{GlobalAlloc, Layout},
+    cell::Cell,
+    ptr,
+};
+
+/// Size of a wasm memory page.
+const PAGE_SIZE: usize = 65536;
+
+#[cfg(target_arch = "wasm32")]
+#[global_allocator]
+static ALLOCATOR: BumpAllocator = BumpAllocator::new(grow_wasm_memory);
+
+/// Grows the memory of the instance by `pages`, returns the previous number of pages or
+/// `usize::MAX` when the host refused.
+#[cfg(target_arch = "wasm32")]
+fn grow_wasm_memory(pages: usize) -> usize {
+    wasm32::memory_grow(0, pages)
+}
+
+struct BumpAllocator {
+    /// address of the next free byte
+    next: Cell<usize>,
+    /// end of the memory requested so far
+    end: Cell<usize>,
+    /// grows the memory like `memory.grow`, replaced in the tests
+    memory_grow: fn(usize) -> usize,
+}
+
+// wasm32 has no threads, the cells are never accessed concurrently
+unsafe impl Sync for BumpAllocator {}
+
+impl BumpAllocator {
+    const fn new(memory_grow: fn(usize) -> usize) -> Self {
+        Self {
+            next: Cell::new(0),
+            end: Cell::new(0),
+            memory_grow,
+        }
+    }
+
+    /// Requests pages from the host so that `size` bytes aligned to `align` fit after the next
+    /// free byte, returns false when the host has no more memory.
+    fn grow(&self, size: usize, align: usize) -> bool {
+        let Some(pages) = size
+            .checked_add(align)
+            .map(|bytes| bytes.div_ceil(PAGE_SIZE))
+        else {
+            return false;
+        };
+        let previous = (self.memory_grow)(pages);
+        if previous == usize::MAX {
+            return false;
+        }
+        let start = previous * PAGE_SIZE;
+        // someone else grew the memory in between, continue in the new pages
+        if start != self.end.get() {
+            self.next.set(start);
+        }
+        self.end.set(start + pages * PAGE_SIZE);
+        true
+    }
+}
+
+unsafe impl GlobalAlloc for BumpAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let fits = |next: usize| {
+            let start = next.checked_next_multiple_of(layout.align())?;
+            let end = start.checked_add(layout.size())?;
+            (end <= self.end.get()).then_some((start, end))
+        };
+        let Some((start, end)) = fits(self.next.get()).or_else(|| {
+            self.grow(layout.size(), layout.align())
+                .then(|| fits(self.next.get()))
+                .flatten()
+        }) else {
+            return ptr::null_mut();
+        };
+        self.next.set(end);
+        start as *mut u8
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        // only the last allocation can be given back
+        if ptr as usize + layout.size() == self.next.get() {
+            self.next.set(ptr as usize);
+        }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        // the last allocation grows and shrinks in place
+        let start = ptr as usize;
+        if start + layout.size() == self.next.get() && start + new_size <= self.end.get() {
+            self.next.set(start + new_size);
+            return ptr;
+        }
+        let Ok(new_layout) = Layout::from_size_align(new_size, layout.align()) else {
+            return ptr::null_mut();
+        };
+        let new_ptr = unsafe { self.alloc(new_layout) };
+        if !new_ptr.is_null() {
+            unsafe {
+                ptr::copy_nonoverlapping(ptr, new_ptr, layout.size().min(new_size));
+                self.dealloc(ptr, layout);
+            }
+        }
+        new_ptr
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{alloc, cell::Cell};
+
+    use super::*;
+
+    thread_local! {
+        /// first page, pages grown so far and pages available of the memory of the test
+        static MEMORY: Cell<(usize, usize, usize)> = const { Cell::new((0, 0, 0)) };
+    }
+
+    /// Grows the memory of the test like `memory.grow`.
+    fn grow_test_memory(pages: usize) -> usize {
+        MEMORY.with(|memory| {
+            let (first, grown, available) = memory.get();
+            if grown + pages > available {
+                return usize::MAX;
+            }
+            memory.set((first, grown + pages, available));
+            first + grown
+        })
+    }
+
+    /// Page aligned buffer that `grow_test_memory` hands out while it lives.
+    struct Memory {
+        start: *mut u8,
+        layout: Layout,
+    }
+
+    impl Memory {
+        fn new(pages: usize) -> Self {
+            let layout = Layout::from_size_align(pages * PAGE_SIZE, PAGE_SIZE).unwrap();
+            let start = unsafe { alloc::alloc(layout) };
+            assert!(!start.is_null());
+            MEMORY.with(|memory| memory.set((start as usize / PAGE_SIZE, 0, pages)));
+            Self { start, layout }
+        }
+    }
+
+    impl Drop for Memory {
+        fn drop(&mut self) {
+            MEMORY.with(|memory| memory.set((0, 0, 0)));
+            unsafe { alloc::dealloc(self.start, self.layout) };
+        }
+    }
+
+    fn layout(size: usize, align: usize) -> Layout {
+        Layout::from_size_align(size, align).unwrap()
+    }
+
+    #[test]
+    fn allocations_are_aligned_and_do_not_overlap() {
+        let memory = Memory::new(4);
+        let allocator = BumpAllocator::new(grow_test_memory);
+        let mut ranges = Vec::new();
+        for (size, align) in [(3, 1), (8, 8), (1, 2), (PAGE_SIZE, 64), (5, 16)] {
+            let ptr = unsafe { allocator.alloc(layout(size, align)) } as usize;
+            assert_eq!(ptr % align, 0);
+            assert!(ptr >= memory.start as usize);
+            assert!(ptr + size <= memory.start as usize + 4 * PAGE_SIZE);
+            ranges.push(ptr..ptr + size);
+        }
+        for (index, range) in ranges.iter().enumerate() {
+            for other in &ranges[index + 1..] {
+                assert!(range.end <= other.start || other.end <= range.start);
+            }
+        }
+    }
+
+    #[test]
+    fn only_the_last_allocation_is_given_back() {
+        let _memory = Memory::new(1);
+        let allocator = BumpAllocator::new(grow_test_memory);
+        let first = unsafe { allocator.alloc(layout(16, 8)) };
+        let second = unsafe { allocator.alloc(layout(16, 8)) };
+        unsafe { allocator.dealloc(first, layout(16, 8)) };
+        assert_ne!(unsafe { allocator.alloc(layout(16, 8)) }, first);
+        let third = unsafe { allocator.alloc(layout(16, 8)) };
+        unsafe { allocator.dealloc(third, layout(16, 8)) };
+        assert_eq!(unsafe { allocator.alloc(layout(16, 8)) }, third);
+        assert_ne!(second, third);
+    }
+
+    #[test]
+    fn the_last_allocation_is_resized_in_place() {
+        let _memory = Memory::new(1);
+        let allocator = BumpAllocator::new(grow_test_memory);
+        let ptr = unsafe { allocator.alloc(layout(16, 8)) };
+        assert_eq!(unsafe { allocator.realloc(ptr, layout(16, 8), 64) }, ptr);
+        assert_eq!(unsafe { allocator.realloc(ptr, layout(64, 8), 8) }, ptr);
+        // the shrunk allocation left the rest free
+        let next = unsafe { allocator.alloc(layout(8, 8)) };
+        assert_eq!(next as usize, ptr as usize + 8);
+    }
+
+    #[test]
+    fn earlier_allocations_move_and_keep_their_content() {
+        let _memory = Memory::new(1);
+        let allocator = BumpAllocator::new(grow_test_memory);
+        let ptr = unsafe { allocator.alloc(layout(4, 1)) };
+        unsafe { ptr::copy_nonoverlapping([1u8, 2, 3, 4].as_ptr(), ptr, 4) };
+        let _last = unsafe { allocator.alloc(layout(4, 1)) };
+        let moved = unsafe { allocator.realloc(ptr, layout(4, 1), 8) };
+        assert_ne!(moved, ptr);
+        assert_eq!(
+            unsafe { core::slice::from_raw_parts(moved, 4) },
+            [1, 2, 3, 4]
+        );
+    }
+
+    #[test]
+    fn allocations_fail_once_the_host_has_no_memory() {
+        let _memory = Memory::new(2);
+        let allocator = BumpAllocator::new(grow_test_memory);
+        // the alignment is reserved as well, so the first page grows the memory by two
+        assert!(!unsafe { allocator.alloc(layout(PAGE_SIZE, 1)) }.is_null());
+        assert!(!unsafe { allocator.alloc(layout(PAGE_SIZE, 1)) }.is_null());
+        assert!(unsafe { allocator.alloc(layout(1, 1)) }.is_null());
+        assert!(unsafe { allocator.alloc(layout(isize::MAX as usize, 1)) }.is_null());
+    }
+
+    #[test]
+    fn allocations_continue_after_memory_grown_by_someone_else() {
+        let memory = Memory::new(3);
+        let allocator = BumpAllocator::new(grow_test_memory);
+        let first = unsafe { allocator.alloc(layout(PAGE_SIZE - 8, 1)) };
+        assert_eq!(first, memory.start);
+        let foreign = grow_test_memory(1) * PAGE_SIZE;
+        let second = unsafe { allocator.alloc(layout(16, 1)) } as usize;
+        assert!(second >= foreign + PAGE_SIZE);
+        assert!(second + 16 <= memory.start as usize + 3 * PAGE_SIZE);
+    }
+}
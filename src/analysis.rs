@@ -0,0 +1,112 @@
+use crate::prelude::*;
+use crate::{
+    WorldState,
+    id_map::{IdMap, IdSet},
+    kinematics::{self, MAX_HORIZON},
+    prediction::{ActionDistribution, ActionPredictor},
+    spatial::SpatialIndex,
+};
+
+/// Analysis of the world state of one tick that does not depend on which own agent decides,
+/// built on the first call of `make_action` after the world state was cleared and shared by the
+/// decisions of all own agents on that tick. It is rebuilt in place, so that its buffers are
+/// reused from tick to tick.
+#[derive(Default)]
+pub struct TickAnalysis {
+    /// whether the analysis belongs to the current world state
+    fresh: bool,
+    /// positions of the ships, for distance queries
+    pub ships: SpatialIndex,
+    /// positions of the shots, for distance queries
+    pub shots: SpatialIndex,
+    /// agents that have a shot in flight
+    shooting_agents: IdSet,
+    /// projected positions of all shots until they expire, each starting with its current
+    /// position
+    shot_paths: Vec<(f32, f32)>,
+    /// offset of the path of each shot in `shot_paths`, the last offset is the total length
+    shot_path_starts: Vec<usize>,
+    /// predicted next actions of every enemy agent while calm and while threatened by our shots
+    predictions: IdMap<[ActionDistribution; 2]>,
+}
+
+impl TickAnalysis {
+    /// Marks the analysis as outdated, it is rebuilt on the next call of [`Self::update`].
+    pub fn invalidate(&mut self) {
+        self.fresh = false;
+    }
+
+    /// Rebuilds the analysis from `world_state` unless it is already up to date.
+    pub fn update(
+        &mut self,
+        world_state: &WorldState,
+        predictor: &ActionPredictor,
+        shot_velocity: f32,
+    ) {
+        if self.fresh {
+            return;
+        }
+        self.fresh = true;
+
+        self.shot_paths.clear();
+        self.shot_path_starts.clear();
+        self.shot_path_starts.push(0);
+        for shot in &world_state.shots {
+            let horizon = (shot.lifetime.max(0) as u32).min(MAX_HORIZON);
+            self.shot_paths.extend(
+                (0..=horizon).map(|tick| kinematics::shot_position(shot, shot_velocity, tick)),
+            );
+            self.shot_path_starts.push(self.shot_paths.len());
+        }
+
+        self.predictions.clear();
+        for ship in world_state.ships.iter().filter(|ship| !ship.friendly) {
+            self.predictions.insert(
+                ship.agent_id,
+                [
+                    predictor.distribution(ship.agent_id, false),
+                    predictor.distribution(ship.agent_id, true),
+                ],
+            );
+        }
+
+        self.ships.rebuild(
+            world_state
+                .ships
+                .iter()
+                .map(|ship| (ship.pos_x, ship.pos_y)),
+        );
+        self.shots.rebuild(
+            world_state
+                .shots
+                .iter()
+                .map(|shot| (shot.pos_x, shot.pos_y)),
+        );
+        self.shooting_agents.clear();
+        self.shooting_agents
+            .extend(world_state.shots.iter().map(|shot| shot.agent_id));
+    }
+
+    /// Whether `agent_id` has a shot in flight, each agent can only have one shot at a time.
+    pub fn has_shot(&self, agent_id: u32) -> bool {
+        self.shooting_agents.contains(agent_id)
+    }
+
+    /// Projected positions of the shot at index `shot`, one per tick starting with the current
+    /// one. Unknown shots have an empty path.
+    pub fn shot_path(&self, shot: usize) -> &[(f32, f32)] {
+        match self.shot_path_starts.get(shot..) {
+            Some(&[start, end, ..]) => self.shot_paths.get(start..end).unwrap_or_default(),
+            _ => &[],
+        }
+    }
+
+    /// Predicted next action of `agent_id`, see [`ActionPredictor::distribution`].
+    pub fn prediction(&self, agent_id: u32, threatened: bool) -> ActionDistribution {
+        self.predictions
+            .get(agent_id)
+            .map_or(crate::prediction::UNIFORM, |predictions| {
+                predictions[usize::from(threatened)]
+            })
+    }
+}
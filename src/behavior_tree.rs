@@ -0,0 +1,343 @@
+use core::fmt::{Display, Formatter};
+
+use crate::behaviors::Blackboard;
+use crate::prelude::*;
+
+/// Result of ticking a node.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Status {
+    Success,
+    Failure,
+    /// the node is not done yet and wants to be ticked again on the next tick
+    Running,
+}
+
+/// Modifies the result of the child of a decorator node.
+pub enum Decorator {
+    /// turns success into failure and the other way round
+    Inverter,
+    /// always succeeds, also while the child is still running, so that the nodes after it
+    /// are ticked as well
+    Succeeder,
+}
+
+/// Children of a selector or sequence, with the child that was still running on the last tick.
+pub struct Composite {
+    children: Vec<Node>,
+    running: Option<usize>,
+}
+
+/// Node of a behavior tree. The tree keeps the running state of its nodes between ticks, so
+/// every agent needs its own tree.
+pub enum Node {
+    /// ticks the children in order until one does not fail, always starting at the first child
+    /// so that higher priority behaviors can interrupt running ones
+    Selector(Composite),
+    /// ticks the children in order until one does not succeed, resuming at the child that was
+    /// still running on the last tick once the conditions before it still hold
+    Sequence(Composite),
+    Decorator(Decorator, Box<Node>),
+    /// succeeds when the check holds, fails otherwise
+    Condition(&'static str, fn(&Blackboard) -> bool),
+    Action(&'static str, fn(&mut Blackboard) -> Status),
+}
+
+impl Node {
+    pub fn selector(children: Vec<Node>) -> Self {
+        Self::Selector(Composite {
+            children,
+            running: None,
+        })
+    }
+
+    pub fn sequence(children: Vec<Node>) -> Self {
+        Self::Sequence(Composite {
+            children,
+            running: None,
+        })
+    }
+
+    pub fn inverter(child: Node) -> Self {
+        Self::Decorator(Decorator::Inverter, Box::new(child))
+    }
+
+    pub fn succeeder(child: Node) -> Self {
+        Self::Decorator(Decorator::Succeeder, Box::new(child))
+    }
+
+    pub fn condition(name: &'static str, check: fn(&Blackboard) -> bool) -> Self {
+        Self::Condition(name, check)
+    }
+
+    pub fn action(name: &'static str, run: fn(&mut Blackboard) -> Status) -> Self {
+        Self::Action(name, run)
+    }
+
+    /// Ticks the node and its children. Every ticked node costs one unit of the budget, nodes
+    /// fail once it is exhausted so that the action built so far is kept.
+    pub fn tick(&mut self, blackboard: &mut Blackboard) -> Status {
+        if !blackboard.budget.try_spend(1) {
+            return Status::Failure;
+        }
+        match self {
+            Self::Selector(composite) => composite.tick_selector(blackboard),
+            Self::Sequence(composite) => composite.tick_sequence(blackboard),
+            Self::Decorator(decorator, child) => match (decorator, child.tick(blackboard)) {
+                (Decorator::Succeeder, _) => Status::Success,
+                (Decorator::Inverter, Status::Running) => Status::Running,
+                (Decorator::Inverter, Status::Success) => Status::Failure,
+                (Decorator::Inverter, Status::Failure) => Status::Success,
+            },
+            Self::Condition(_, check) => {
+                if check(blackboard) {
+                    Status::Success
+                } else {
+                    Status::Failure
+                }
+            }
+            Self::Action(name, run) => {
+                let status = run(blackboard);
+                if status != Status::Failure {
+                    blackboard.behaviors.push(name);
+                }
+                status
+            }
+        }
+    }
+
+    /// Forgets the running state of the node and its children, used when a running node got
+    /// interrupted.
+    fn reset(&mut self) {
+        match self {
+            Self::Selector(composite) | Self::Sequence(composite) => {
+                composite.running = None;
+                composite.children.iter_mut().for_each(Node::reset);
+            }
+            Self::Decorator(_, child) => child.reset(),
+            Self::Condition(..) | Self::Action(..) => (),
+        }
+    }
+}
+
+impl Composite {
+    fn tick_selector(&mut self, blackboard: &mut Blackboard) -> Status {
+        for index in 0..self.children.len() {
+            let status = self.children[index].tick(blackboard);
+            if status != Status::Failure {
+                // a child with higher priority took over from the one that was running
+                if let Some(running) = self.running
+                    && running != index
+                {
+                    self.children[running].reset();
+                }
+                self.running = (status == Status::Running).then_some(index);
+                return status;
+            }
+        }
+        if let Some(running) = self.running.take() {
+            self.children[running].reset();
+        }
+        Status::Failure
+    }
+
+    fn tick_sequence(&mut self, blackboard: &mut Blackboard) -> Status {
+        let start = self.running.take().unwrap_or(0);
+        for index in 0..self.children.len() {
+            // the conditions before the running child are checked again, so that a sequence
+            // does not resume once its guard no longer holds
+            if index < start && !matches!(self.children[index], Node::Condition(..)) {
+                continue;
+            }
+            match self.children[index].tick(blackboard) {
+                Status::Success => (),
+                Status::Running => {
+                    self.running = Some(index);
+                    return Status::Running;
+                }
+                Status::Failure => {
+                    if index < start {
+                        self.children[start].reset();
+                    }
+                    return Status::Failure;
+                }
+            }
+        }
+        Status::Success
+    }
+}
+
+impl Display for Node {
+    fn fmt(&self, formatter: &mut Formatter) -> Result<(), core::fmt::Error> {
+        let (name, children) = match self {
+            Self::Selector(composite) => ("selector", composite.children.iter().collect()),
+            Self::Sequence(composite) => ("sequence", composite.children.iter().collect()),
+            Self::Decorator(Decorator::Inverter, child) => ("not", vec![child.as_ref()]),
+            Self::Decorator(Decorator::Succeeder, child) => ("succeed", vec![child.as_ref()]),
+            Self::Condition(name, _) => return write!(formatter, "{name}?"),
+            Self::Action(name, _) => return write!(formatter, "{name}"),
+        };
+        write!(formatter, "{name}(")?;
+        for (index, child) in children.into_iter().enumerate() {
+            if index > 0 {
+                write!(formatter, ", ")?;
+            }
+            write!(formatter, "{child}")?;
+        }
+        write!(formatter, ")")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Context, Ship, analysis::TickAnalysis, behaviors::Scratch, budget::Budget};
+
+    fn succeed(_: &mut Blackboard) -> Status {
+        Status::Success
+    }
+
+    fn fail(_: &mut Blackboard) -> Status {
+        Status::Failure
+    }
+
+    fn keep_running(_: &mut Blackboard) -> Status {
+        Status::Running
+    }
+
+    fn never(_: &Blackboard) -> bool {
+        false
+    }
+
+    /// Holds on even ticks, so that tests can switch a branch on and off.
+    fn even_tick(blackboard: &Blackboard) -> bool {
+        blackboard.tick.is_multiple_of(2)
+    }
+
+    /// Ticks `tree` once per entry of `ticks` on an empty world and returns the status and the
+    /// actions that ran on each tick.
+    fn run(tree: &mut Node, budget: u32, ticks: &[u32]) -> Vec<(Status, Vec<&'static str>)> {
+        let ctx = Context::default();
+        let mut analysis = TickAnalysis::default();
+        analysis.update(&ctx.world_state, &ctx.predictor, 0.0);
+        let ship = Ship::default();
+        let mut scratch = Scratch::default();
+        let mut results = Vec::new();
+        for &tick in ticks {
+            let mut blackboard = Blackboard::sense(
+                &ctx,
+                &analysis,
+                &ship,
+                0,
+                tick,
+                Budget::new(budget),
+                scratch,
+            );
+            let status = tree.tick(&mut blackboard);
+            results.push((status, blackboard.behaviors.clone()));
+            scratch = blackboard.into_scratch();
+        }
+        results
+    }
+
+    #[test]
+    fn selector_stops_at_the_first_child_that_does_not_fail() {
+        let mut tree = Node::selector(vec![
+            Node::condition("never", never),
+            Node::action("fail", fail),
+            Node::action("a", succeed),
+            Node::action("b", succeed),
+        ]);
+        assert_eq!(run(&mut tree, 100, &[1]), [(Status::Success, vec!["a"])]);
+    }
+
+    #[test]
+    fn sequence_stops_at_the_first_child_that_does_not_succeed() {
+        let mut tree = Node::sequence(vec![
+            Node::action("a", succeed),
+            Node::condition("never", never),
+            Node::action("b", succeed),
+        ]);
+        assert_eq!(run(&mut tree, 100, &[1]), [(Status::Failure, vec!["a"])]);
+    }
+
+    #[test]
+    fn running_sequence_resumes_at_the_running_child() {
+        let mut tree = Node::sequence(vec![
+            Node::action("start", succeed),
+            Node::action("run", keep_running),
+        ]);
+        assert_eq!(
+            run(&mut tree, 100, &[1, 2]),
+            [
+                (Status::Running, vec!["start", "run"]),
+                (Status::Running, vec!["run"])
+            ]
+        );
+    }
+
+    #[test]
+    fn resumed_sequence_fails_once_its_condition_no_longer_holds() {
+        let mut tree = Node::sequence(vec![
+            Node::condition("even tick", even_tick),
+            Node::action("start", succeed),
+            Node::action("run", keep_running),
+        ]);
+        assert_eq!(
+            run(&mut tree, 100, &[2, 4, 5, 6]),
+            [
+                (Status::Running, vec!["start", "run"]),
+                (Status::Running, vec!["run"]),
+                (Status::Failure, vec![]),
+                // the sequence starts over instead of resuming
+                (Status::Running, vec!["start", "run"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn higher_priority_child_interrupts_and_resets_the_running_one() {
+        let mut tree = Node::selector(vec![
+            Node::sequence(vec![
+                Node::condition("even tick", even_tick),
+                Node::action("urgent", succeed),
+            ]),
+            Node::sequence(vec![
+                Node::action("start", succeed),
+                Node::action("run", keep_running),
+            ]),
+        ]);
+        assert_eq!(
+            run(&mut tree, 100, &[1, 3, 4, 5]),
+            [
+                (Status::Running, vec!["start", "run"]),
+                (Status::Running, vec!["run"]),
+                (Status::Success, vec!["urgent"]),
+                // the interrupted sequence starts over
+                (Status::Running, vec!["start", "run"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn decorators_change_the_status_of_their_child() {
+        let mut inverted_failure = Node::inverter(Node::action("fail", fail));
+        assert_eq!(run(&mut inverted_failure, 100, &[1])[0].0, Status::Success);
+        let mut inverted_success = Node::inverter(Node::action("a", succeed));
+        assert_eq!(run(&mut inverted_success, 100, &[1])[0].0, Status::Failure);
+        let mut inverted_running = Node::inverter(Node::action("run", keep_running));
+        assert_eq!(run(&mut inverted_running, 100, &[1])[0].0, Status::Running);
+        let mut succeeder = Node::succeeder(Node::action("run", keep_running));
+        assert_eq!(run(&mut succeeder, 100, &[1])[0].0, Status::Success);
+    }
+
+    #[test]
+    fn nodes_fail_once_the_budget_is_exhausted() {
+        let mut tree = Node::sequence(vec![
+            Node::action("a", succeed),
+            Node::action("b", succeed),
+            Node::action("c", succeed),
+        ]);
+        // the sequence and two of its children fit
+        assert_eq!(run(&mut tree, 3, &[1]), [(Status::Failure, vec!["a", "b"])]);
+    }
+}
@@ -0,0 +1,717 @@
+use core::cell::OnceCell;
+
+#[cfg(target_arch = "wasm32")]
+use crate::math::Float;
+use crate::prelude::*;
+use crate::{
+    Action, AimMode, Context, Ship, Shot, TurnDirection,
+    analysis::TickAnalysis,
+    behavior_tree::{Node, Status},
+    bindings,
+    budget::Budget,
+    collision,
+    kinematics::{self, KinematicState, Kinematics},
+    line_of_fire, log, numeric,
+    opponents::Archetype,
+    prediction::{self, MOVEMENT_ACTIONS},
+    spatial,
+    state_machine::ShipMode,
+    utility,
+};
+
+/// Minimal chance to hit the target with which a shot is fired when aiming dodge aware.
+const MIN_HIT_PROBABILITY: f32 = 0.5;
+
+/// Angle (in radians) by which the approach to a camping target is offset while it faces us.
+const FLANK_ANGLE: f32 = core::f32::consts::FRAC_PI_6;
+
+/// Everything the behaviors of one agent need to know on the current tick, and the action they
+/// are building.
+pub struct Blackboard<'a> {
+    pub tick: u32,
+    pub own_agent_id: u32,
+    pub own_hp: i32,
+    pub own_state: KinematicState,
+    pub kinematics: Kinematics,
+    /// analysis of the tick shared with the other own agents
+    pub analysis: &'a TickAnalysis,
+    pub ships: &'a [Ship],
+    pub shots: &'a [Shot],
+    pub hit_radius: f32,
+    pub shot_velocity: f32,
+    pub shot_lifetime: u32,
+    /// whether our agent can fire, each agent can only have one shot at a time
+    pub shot_available: bool,
+    /// action that steers away from the most dangerous shot, if a shot would hit us
+    pub evade_action: Option<Action>,
+    /// indices into `shots` of the shots that would hit us if we don't evade them
+    pub threats: Vec<usize>,
+    pub aim: Option<Aim>,
+    /// whether no ally is in the line of fire, checked at most once per tick
+    line_of_fire_clear: OnceCell<bool>,
+    /// the action that is returned to the host
+    pub action: Action,
+    /// mode of our ship, set by its mode machine before the behaviors run
+    pub mode: ShipMode,
+    /// names of the action nodes that ran on this tick
+    pub behaviors: Vec<&'static str>,
+    /// work that may still be done for this decision
+    pub budget: Budget,
+    /// shots that can reach us with their distance, reused while detecting threats
+    nearby_shots: Vec<(f32, usize)>,
+    /// result of the target query
+    nearest: Vec<(usize, f32)>,
+}
+
+/// Buffers of the blackboard that are kept between decisions, so that they don't have to be
+/// allocated again on every decision.
+#[derive(Default)]
+pub struct Scratch {
+    threats: Vec<usize>,
+    behaviors: Vec<&'static str>,
+    nearby_shots: Vec<(f32, usize)>,
+    nearest: Vec<(usize, f32)>,
+}
+
+/// How to get the current target in our sights.
+pub struct Aim {
+    pub target: Ship,
+    pub distance: f32,
+    pub archetype: Archetype,
+    pub fire_range: f32,
+    /// turn that brings the target into our sights
+    pub movement: Option<TurnDirection>,
+    /// whether a shot fired now would hit the target
+    pub on_target: bool,
+}
+
+impl<'a> Blackboard<'a> {
+    /// Evaluates the world state from the perspective of `own_ship`, the simulations this needs
+    /// are paid from `budget`.
+    pub fn sense(
+        ctx: &'a Context,
+        analysis: &'a TickAnalysis,
+        own_ship: &Ship,
+        own_agent_id: u32,
+        tick: u32,
+        budget: Budget,
+        mut scratch: Scratch,
+    ) -> Self {
+        scratch.threats.clear();
+        scratch.behaviors.clear();
+        let kinematics = Kinematics::new(&ctx.config, &ctx.physics);
+        let own_state = KinematicState::from(own_ship);
+        let shot_velocity = ctx.physics.shot_velocity(&ctx.config);
+        let shot_lifetime = if ctx.config.shot_lifetime > 0.0 {
+            ctx.config.shot_lifetime as u32
+        } else {
+            kinematics::MAX_HORIZON
+        };
+        let mut blackboard = Self {
+            tick,
+            own_agent_id,
+            own_hp: own_ship.hp,
+            own_state,
+            kinematics,
+            analysis,
+            ships: &ctx.world_state.ships,
+            shots: &ctx.world_state.shots,
+            hit_radius: ctx.config.ship_hit_radius,
+            shot_velocity,
+            shot_lifetime,
+            shot_available: true,
+            evade_action: None,
+            threats: scratch.threats,
+            aim: None,
+            line_of_fire_clear: OnceCell::new(),
+            action: Action::default(),
+            mode: ShipMode::default(),
+            behaviors: scratch.behaviors,
+            budget,
+            nearby_shots: scratch.nearby_shots,
+            nearest: scratch.nearest,
+        };
+        blackboard.detect_threats(ctx, own_ship);
+        blackboard.aim = blackboard.acquire_target(ctx, own_ship);
+        blackboard
+    }
+
+    /// Hands the buffers back for the next decision.
+    pub fn into_scratch(self) -> Scratch {
+        Scratch {
+            threats: self.threats,
+            behaviors: self.behaviors,
+            nearby_shots: self.nearby_shots,
+            nearest: self.nearest,
+        }
+    }
+    /// Checks which shots would hit our ship and determines how to evade them.
+    fn detect_threats(&mut self, ctx: &Context, own_ship: &Ship) {
+        let own_agent_id = self.own_agent_id;
+        let tick = self.tick;
+
+        self.shot_available = !self.analysis.has_shot(own_agent_id);
+        // stores indices of shots that can reach the ship before they expire, with the distance
+        // they are away from the ship
+        let own_position = (own_ship.pos_x, own_ship.pos_y);
+        let mut shots = core::mem::take(&mut self.nearby_shots);
+        shots.clear();
+        shots.extend(
+            self.analysis
+                .shots
+                .within(own_position, self.threat_radius())
+                .map(|(index, distance)| (distance, index)),
+        );
+        shots.sort_unstable_by(|a, b| a.0.total_cmp(&b.0));
+        // shot with lowest distance to ship is popped first
+        shots.reverse();
+
+        // iterate through shots and calculate if they would hit
+        // first shot that is determined to hit the ship will be tried to be evaded
+        for &(_, index) in &shots {
+            let Some(shot) = ctx.world_state.shots.get(index) else {
+                continue;
+            };
+            // calculate if shot is in hit radius
+
+            // shortest offset from the shot to my ship, the playfield wraps around
+            let (dx, dy) = spatial::wrapped_delta((shot.pos_x, shot.pos_y), own_position);
+
+            // target = my ship
+            let target_angle = dy.atan2(dx);
+            // current angle between the shot and the ship
+            let current_angle = own_ship.heading;
+
+            // Smallest signed angle difference (-pi .. pi)
+            let angle_diff = numeric::normalize_angle(target_angle - current_angle);
+
+            if angle_diff.to_degrees() <= -179.0 || angle_diff.to_degrees() >= 179.0 {
+                // shot is probably not a danger for the ship
+                continue;
+            }
+
+            log!(
+                "[Tick {}] Agent: {own_agent_id}: detected shot {}/{}, angle_diff {}",
+                tick,
+                shot.pos_x,
+                shot.pos_y,
+                angle_diff.to_degrees()
+            );
+
+            // calculate if shot is in hit radius
+            let lateral_distance_target = numeric::lateral_distance((dx, dy), current_angle);
+            let hit_radius = self.evade_radius();
+
+            // shot would hit ship
+            if lateral_distance_target <= hit_radius {
+                // turn to the side on which the predicted path keeps more distance to the shot,
+                // without budget turn away from the side the shot comes from
+                let shot_path = self.analysis.shot_path(index);
+                let direction = if self.budget.try_spend(2 * shot_path.len() as u32) {
+                    let thrust = bindings::ActionFlags_ACTION_THRUST;
+                    let clearance_left = self.kinematics.clearance(
+                        &self.own_state,
+                        thrust | bindings::ActionFlags_ACTION_TURN_LEFT,
+                        shot_path,
+                    );
+                    let clearance_right = self.kinematics.clearance(
+                        &self.own_state,
+                        thrust | bindings::ActionFlags_ACTION_TURN_RIGHT,
+                        shot_path,
+                    );
+                    if clearance_left > clearance_right
+                        || (clearance_left == clearance_right && angle_diff > 0.0)
+                    {
+                        TurnDirection::Left
+                    } else {
+                        TurnDirection::Right
+                    }
+                } else if angle_diff > 0.0 {
+                    TurnDirection::Left
+                } else {
+                    TurnDirection::Right
+                };
+
+                log!(
+                    "[Tick {}] Agent: {own_agent_id}: evading shot {}/{}, angle_diff {}, turning direction: {}",
+                    tick,
+                    shot.pos_x,
+                    shot.pos_y,
+                    angle_diff.to_degrees(),
+                    direction
+                );
+                self.evade_action = Some(Action {
+                    turn_direction: Some(direction),
+                    enable_thrusters: true,
+                    ..Default::default()
+                });
+                self.threats.push(index);
+            }
+        }
+        self.nearby_shots = shots;
+    }
+
+    /// Locks on to the nearest enemy and determines how to aim at it.
+    fn acquire_target(&mut self, ctx: &Context, own_ship: &Ship) -> Option<Aim> {
+        let own_agent_id = self.own_agent_id;
+        let tick = self.tick;
+
+        let own_position = (own_ship.pos_x, own_ship.pos_y);
+        let ships = &ctx.world_state.ships;
+        // we don't want to lock an allied ship as target
+        self.analysis.ships.nearest(
+            own_position,
+            1,
+            |index| {
+                ships
+                    .get(index)
+                    .is_some_and(|ship| !ship.friendly && ship.agent_id != own_ship.agent_id)
+            },
+            &mut self.nearest,
+        );
+        let target = self.nearest.first().and_then(|&(index, distance)| {
+            // move the target next to us, so that it can be aimed at across the playfield edge
+            let mut target = *ships.get(index)?;
+            let (dx, dy) = spatial::wrapped_delta(own_position, (target.pos_x, target.pos_y));
+            target.pos_x = own_ship.pos_x + dx;
+            target.pos_y = own_ship.pos_y + dy;
+            Some((distance, target))
+        });
+        let Some((distance, target)) = target else {
+            // no target found, so game *should* be won already
+            log!("[Tick {}] Agent: {own_agent_id} - no target found", tick);
+            return None;
+        };
+
+        // find direction in which the target is
+        let x1 = target.pos_x;
+        let y1 = target.pos_y;
+        let x2 = own_ship.pos_x;
+        let y2 = own_ship.pos_y;
+
+        let target_angle = (y1 - y2).atan2(x1 - x2);
+        let current_angle = own_ship.heading;
+
+        // Smallest signed angle difference (-pi .. pi)
+        let angle_diff = numeric::normalize_angle(target_angle - current_angle);
+
+        // pick a counter to the observed behavior of the target
+        let archetype = ctx.opponents.archetype(target.agent_id);
+        let fire_range = match archetype {
+            // dodgers have less time to react to shots fired from close range
+            Archetype::Dodger => 0.2,
+            _ => 0.3,
+        };
+        let mut steer_diff = angle_diff;
+        if archetype == Archetype::Camper && distance > fire_range {
+            // campers wait for us to fly into their line of fire, approach them from the side instead
+            let bearing_to_us = target_angle + core::f32::consts::PI;
+            let facing_diff = numeric::normalize_angle(bearing_to_us - target.heading);
+            if facing_diff.abs() < FLANK_ANGLE {
+                steer_diff += if facing_diff > 0.0 {
+                    FLANK_ANGLE
+                } else {
+                    -FLANK_ANGLE
+                };
+            }
+        }
+
+        // don't turn when the remaining angle is smaller than half a turn, we would overshoot
+        let mut movement = if steer_diff.abs() < (self.kinematics.turn_rate / 2.0).max(0.01) {
+            None
+        } else if steer_diff > 0.0 {
+            Some(TurnDirection::Left)
+        } else {
+            Some(TurnDirection::Right)
+        };
+
+        // calculate if shot is in hit radius, a target on top of us is always hit
+        let offset = (x1 - x2, y1 - y2);
+        let lateral_distance_target = numeric::lateral_distance(offset, current_angle);
+        let hit_radius = self.hit_radius;
+        let mut on_target = distance <= hit_radius
+            || (numeric::forward_distance(offset, current_angle) > 0.0
+                && lateral_distance_target <= hit_radius);
+
+        // the predicted actions are only usable when the shot physics are known
+        let mut aim_mode = if self.shot_velocity > 0.0 {
+            ctx.aim_mode
+        } else {
+            AimMode::Direct
+        };
+        // the target is predicted for our heading and after every turn
+        let prediction_cost =
+            4 * MOVEMENT_ACTIONS.len() as u32 * self.shot_lifetime.min(kinematics::MAX_HORIZON);
+        if aim_mode == AimMode::DodgeAware
+            && distance <= fire_range
+            && !self.budget.try_spend(prediction_cost)
+        {
+            log!(
+                "[Tick {}] Agent: {own_agent_id}, budget exhausted, aiming direct",
+                tick
+            );
+            aim_mode = AimMode::Direct;
+        }
+        if aim_mode == AimMode::DodgeAware && distance <= fire_range {
+            let distribution = self.analysis.prediction(target.agent_id, true);
+            let hit_probability = |state: &KinematicState| {
+                prediction::hit_probability(
+                    &self.kinematics,
+                    state,
+                    &target,
+                    &distribution,
+                    self.shot_velocity,
+                    self.shot_lifetime,
+                    hit_radius,
+                )
+            };
+            let fire_probability = hit_probability(&self.own_state);
+            on_target = fire_probability >= MIN_HIT_PROBABILITY;
+            if !on_target {
+                // turn to where a shot on the next tick has the best chance to hit
+                let (best_turn, best_probability) =
+                    [None, Some(TurnDirection::Left), Some(TurnDirection::Right)]
+                        .into_iter()
+                        .map(|turn| {
+                            let action =
+                                bindings::ActionFlags_ACTION_THRUST | turn.map_or(0, u32::from);
+                            (
+                                turn,
+                                hit_probability(&self.kinematics.step(&self.own_state, action)),
+                            )
+                        })
+                        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+                        .unwrap_or((None, 0.0));
+                if best_probability > fire_probability {
+                    movement = best_turn;
+                }
+            }
+        }
+
+        log!(
+            "[Tick {}] Agent: {own_agent_id}, Current position: [{},{}], Target direction: {}, Current direction: {}, Target in scope: {}",
+            tick,
+            own_ship.pos_x,
+            own_ship.pos_y,
+            90.0 - target_angle.to_degrees(),
+            90.0 - current_angle.to_degrees(),
+            on_target
+        );
+
+        Some(Aim {
+            target,
+            distance,
+            archetype,
+            fire_range,
+            movement,
+            on_target,
+        })
+    }
+
+    /// Whether a shot fired now can't hit an ally, not even after missing the target.
+    pub fn line_of_fire_clear(&self) -> bool {
+        *self.line_of_fire_clear.get_or_init(|| {
+            let corridor = 2.0 * self.hit_radius;
+            // allies further away can't get into the path of the shot before it expires
+            let reach = (self.shot_velocity + self.kinematics.max_velocity)
+                * self.shot_lifetime as f32
+                + corridor;
+            let candidates = self
+                .analysis
+                .ships
+                .within((self.own_state.pos_x, self.own_state.pos_y), reach)
+                .filter_map(|(index, _)| self.ships.get(index));
+            let Some(ally) = line_of_fire::blocking_ally(
+                &self.own_state,
+                self.own_agent_id,
+                candidates,
+                self.shot_velocity,
+                self.shot_lifetime,
+                corridor,
+            ) else {
+                return true;
+            };
+            log!(
+                "[Tick {}] Agent: {}, holding fire, agent {ally} is in the line of fire",
+                self.tick,
+                self.own_agent_id,
+            );
+            false
+        })
+    }
+
+    /// Distance within which a passing shot is evaded. It is larger than the hit radius to make
+    /// evasion easier and more conservative.
+    pub fn evade_radius(&self) -> f32 {
+        self.hit_radius + (self.hit_radius + 0.0625)
+    }
+
+    /// Distance from which a shot can still reach our ship before it expires, when both fly
+    /// straight at each other.
+    fn threat_radius(&self) -> f32 {
+        let radius = (self.shot_velocity + self.kinematics.max_velocity)
+            * self.shot_lifetime as f32
+            + self.evade_radius();
+        if radius.is_finite() && radius > 0.0 {
+            radius
+        } else {
+            // without known physics every shot is a potential threat
+            spatial::PLAYFIELD_SIZE
+        }
+    }
+
+    /// Whether the thrusters should be enabled while attacking.
+    fn attack_thrusters(&self) -> bool {
+        // chasers come to us anyway, wait for them to fly into our shot
+        let chaser = self
+            .aim
+            .as_ref()
+            .is_some_and(|aim| aim.archetype == Archetype::Chaser);
+        !(chaser && self.shot_available)
+    }
+}
+
+/// Builds the default behavior of an agent: the mode of our ship picks whether we fire, evade
+/// shots, retreat, aim at the target or fly straight, and distance to other ships is kept on top
+/// of that unless evading.
+pub fn default_tree() -> Node {
+    Node::sequence(vec![
+        Node::succeeder(Node::selector(vec![
+            Node::sequence(vec![
+                Node::condition("firing", firing),
+                Node::action("fire", fire),
+            ]),
+            Node::sequence(vec![
+                Node::condition("evading", evading),
+                Node::action("evade", evade),
+            ]),
+            Node::sequence(vec![
+                Node::condition("retreating", retreating),
+                Node::action("retreat", retreat),
+            ]),
+            Node::sequence(vec![
+                Node::condition("attacking", attacking),
+                Node::action("aim", aim),
+            ]),
+            Node::sequence(vec![
+                Node::condition("reloading", reloading),
+                Node::action("fly straight", fly_straight),
+            ]),
+            Node::action("idle", idle),
+        ])),
+        // dodging shots is more important than keeping distance to ships
+        Node::succeeder(Node::sequence(vec![
+            Node::inverter(Node::condition("evading", evading)),
+            Node::action("avoid collisions", avoid_collisions),
+        ])),
+    ])
+}
+
+fn firing(blackboard: &Blackboard) -> bool {
+    blackboard.mode == ShipMode::Firing
+}
+
+fn evading(blackboard: &Blackboard) -> bool {
+    blackboard.mode == ShipMode::Evading
+}
+
+fn retreating(blackboard: &Blackboard) -> bool {
+    blackboard.mode == ShipMode::Retreating
+}
+
+fn attacking(blackboard: &Blackboard) -> bool {
+    matches!(blackboard.mode, ShipMode::Hunting | ShipMode::Aiming)
+}
+
+fn reloading(blackboard: &Blackboard) -> bool {
+    blackboard.mode == ShipMode::Reloading
+}
+
+pub fn shot_available(blackboard: &Blackboard) -> bool {
+    blackboard.shot_available
+}
+
+pub fn target_in_scope(blackboard: &Blackboard) -> bool {
+    blackboard
+        .aim
+        .as_ref()
+        .is_some_and(|aim| aim.on_target && aim.distance <= aim.fire_range)
+}
+
+pub fn line_of_fire_clear(blackboard: &Blackboard) -> bool {
+    blackboard.line_of_fire_clear()
+}
+
+pub fn fire(blackboard: &mut Blackboard) -> Status {
+    blackboard.action = Action {
+        // don't turn to not distort the shot
+        turn_direction: None,
+        enable_thrusters: blackboard.attack_thrusters(),
+        fire: true,
+    };
+    Status::Success
+}
+
+/// Steers away from danger, takes priority over everything but firing a shot that would hit.
+/// Instead of always taking the evade action of the threat detection, every candidate action
+/// is rated, so that the target or the allies can tip the choice between ways to get clear of
+/// the shot. Turns against the side the evasion started with are left out. Without budget for
+/// the ratings the evade action is taken.
+pub fn evade(blackboard: &mut Blackboard) -> Status {
+    let Some(evade_action) = blackboard.evade_action else {
+        return Status::Failure;
+    };
+    let side = evade_action.turn_direction;
+    let rating = utility::choose(blackboard, |candidate| {
+        candidate.turn_direction.is_none() || candidate.turn_direction == side
+    });
+    blackboard.action = match rating {
+        Some(rating) => {
+            log!(
+                "[Tick {}] Agent: {}, evading, {rating}",
+                blackboard.tick,
+                blackboard.own_agent_id
+            );
+            rating.action
+        }
+        None => {
+            log!(
+                "[Tick {}] Agent: {}, evading",
+                blackboard.tick,
+                blackboard.own_agent_id
+            );
+            evade_action
+        }
+    };
+    Status::Success
+}
+
+/// Turns away from the target at full thrust, to get out of its range while we can't fire.
+pub fn retreat(blackboard: &mut Blackboard) -> Status {
+    let Some(aim) = &blackboard.aim else {
+        return Status::Failure;
+    };
+    // turn the other way than we would to aim at the target
+    let turn_direction = match aim.movement {
+        Some(TurnDirection::Left) => TurnDirection::Right,
+        _ => TurnDirection::Left,
+    };
+    blackboard.action = Action {
+        turn_direction: Some(turn_direction),
+        enable_thrusters: true,
+        fire: false,
+    };
+    Status::Success
+}
+
+pub fn aim(blackboard: &mut Blackboard) -> Status {
+    let Some(aim) = &blackboard.aim else {
+        return Status::Failure;
+    };
+    blackboard.action = Action {
+        turn_direction: aim.movement,
+        enable_thrusters: blackboard.attack_thrusters(),
+        fire: false,
+    };
+    Status::Success
+}
+
+/// Flies straight while no shot is available, to not fly into enemies while turning. Keeps
+/// running until firing, evading or aiming takes over again.
+pub fn fly_straight(blackboard: &mut Blackboard) -> Status {
+    log!(
+        "[Tick {}] Agent: {}, No shot available, flying straight",
+        blackboard.tick,
+        blackboard.own_agent_id
+    );
+    blackboard.action = Action {
+        turn_direction: None,
+        enable_thrusters: blackboard.attack_thrusters(),
+        fire: false,
+    };
+    Status::Running
+}
+
+/// Cheap action used when the budget ran out before a planner found one: evade when a shot would
+/// hit us, otherwise fire when the target is in scope or turn towards it.
+pub fn fallback_action(blackboard: &Blackboard) -> Action {
+    if let Some(evade_action) = blackboard.evade_action {
+        return evade_action;
+    }
+    let Some(aim) = &blackboard.aim else {
+        return Action::default();
+    };
+    if shot_available(blackboard) && target_in_scope(blackboard) && line_of_fire_clear(blackboard) {
+        return Action {
+            turn_direction: None,
+            enable_thrusters: false,
+            fire: true,
+        };
+    }
+    Action {
+        turn_direction: aim.movement,
+        enable_thrusters: true,
+        fire: false,
+    }
+}
+
+pub fn idle(blackboard: &mut Blackboard) -> Status {
+    blackboard.action = Action::default();
+    Status::Success
+}
+
+/// Changes the action when it would bring us too close to another ship.
+pub fn avoid_collisions(blackboard: &mut Blackboard) -> Status {
+    // the current action and at most six candidates are simulated
+    let cost = 7 * collision::COLLISION_HORIZON * blackboard.ships.len() as u32;
+    if !blackboard.budget.try_spend(cost) {
+        return Status::Failure;
+    }
+    let action = blackboard.action;
+    let margin = |candidate: Action| {
+        collision::collision_margin(
+            &blackboard.kinematics,
+            &blackboard.own_state,
+            blackboard.own_agent_id,
+            blackboard.ships,
+            candidate.into(),
+            blackboard.hit_radius,
+        )
+    };
+    let current_margin = margin(action);
+    if current_margin >= 0.0 {
+        return Status::Failure;
+    }
+    // turning while firing would distort the shot, so only the thrusters may change
+    let turns: &[Option<TurnDirection>] = if action.fire {
+        &[None]
+    } else {
+        &[None, Some(TurnDirection::Left), Some(TurnDirection::Right)]
+    };
+    let best = turns
+        .iter()
+        .copied()
+        .flat_map(|turn_direction| {
+            [true, false].map(|enable_thrusters| Action {
+                enable_thrusters,
+                turn_direction,
+                fire: action.fire,
+            })
+        })
+        .map(|candidate| (candidate, margin(candidate)))
+        .max_by(|(_, a), (_, b)| a.total_cmp(b));
+    match best {
+        Some((candidate, candidate_margin)) if candidate_margin > current_margin => {
+            log!(
+                "[Tick {}] Agent: {}, avoiding ship collision, margin {} -> {}",
+                blackboard.tick,
+                blackboard.own_agent_id,
+                current_margin,
+                candidate_margin
+            );
+            blackboard.action = candidate;
+            Status::Success
+        }
+        _ => Status::Failure,
+    }
+}
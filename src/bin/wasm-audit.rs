@@ -0,0 +1,927 @@
+//! Audits the built wasm module of the agent: lists its exports and imports, checks that it
+//! exports exactly the functions of `scubywasm_agent.h` with their signatures and imports nothing
+//! but `debug.debug_log`, and reports the code size per function, largest first.
+//!
+//! Usage: `cargo run --bin wasm-audit [module]`, by default the release build for
+//! wasm32-unknown-unknown is audited. Exits with 1 when the module does not match the header.
+
+use std::{collections::HashMap, env, fmt::Display, fs, process::ExitCode};
+
+/// Name and wasm signature of every function in `scubywasm_agent.h`.
+const EXPORTS: &[(&str, &str)] = include!(concat!(env!("OUT_DIR"), "/wasm_exports.rs"));
+
+/// Module, name and wasm signature of the functions the agent imports from the host.
+const IMPORTS: &[(&str, &str, &str)] = &[("debug", "debug_log", "(i32, i32)")];
+
+const DEFAULT_MODULE: &str = "target/wasm32-unknown-unknown/release/quick_start_simple.wasm";
+
+/// Largest functions listed in the size report, the others are summed up.
+const LISTED_FUNCTIONS: usize = 20;
+
+/// Parts of the function names that belong to the formatting and the panic machinery. Floats
+/// are formatted by `core::num::flt2dec` with the big numbers of `core::num::bignum`.
+const FORMATTING: &[&str] = &[
+    "core::fmt",
+    "alloc::fmt",
+    "core::num::fmt",
+    "core::num::flt2dec",
+    "core::num::bignum",
+];
+const PANICKING: &[&str] = &["panic"];
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Function,
+    Table,
+    Memory,
+    Global,
+    Tag,
+}
+
+impl Display for Kind {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str(match self {
+            Self::Function => "function",
+            Self::Table => "table",
+            Self::Memory => "memory",
+            Self::Global => "global",
+            Self::Tag => "tag",
+        })
+    }
+}
+
+struct Import {
+    module: String,
+    name: String,
+    kind: Kind,
+    /// signature of imported functions
+    signature: Option<String>,
+}
+
+struct Export {
+    name: String,
+    kind: Kind,
+    index: u32,
+}
+
+/// The parts of a wasm module the audit looks at.
+#[derive(Default)]
+struct Module {
+    /// function signatures of the type section, like `(i32, f32) -> i32`
+    types: Vec<String>,
+    imports: Vec<Import>,
+    /// type indices of the functions defined in the module
+    functions: Vec<u32>,
+    exports: Vec<Export>,
+    /// body sizes in bytes of the functions defined in the module
+    code_sizes: Vec<usize>,
+    /// names of the name section by function index
+    names: HashMap<u32, String>,
+}
+
+impl Module {
+    fn imported_functions(&self) -> u32 {
+        self.imports
+            .iter()
+            .filter(|import| import.kind == Kind::Function)
+            .count() as u32
+    }
+
+    /// Signature of the function with `index`, imported functions come first.
+    fn signature(&self, index: u32) -> Option<&str> {
+        let type_index = match index.checked_sub(self.imported_functions()) {
+            Some(defined) => *self.functions.get(defined as usize)?,
+            None => {
+                let import = self
+                    .imports
+                    .iter()
+                    .filter(|import| import.kind == Kind::Function)
+                    .nth(index as usize)?;
+                return import.signature.as_deref();
+            }
+        };
+        self.types.get(type_index as usize).map(String::as_str)
+    }
+
+    /// Demangled name of the function with `index`, falls back to its export name.
+    fn function_name(&self, index: u32) -> String {
+        if let Some(name) = self.names.get(&index) {
+            return demangle(name);
+        }
+        self.exports
+            .iter()
+            .find(|export| export.kind == Kind::Function && export.index == index)
+            .map_or_else(|| format!("function {index}"), |export| export.name.clone())
+    }
+}
+
+fn main() -> ExitCode {
+    let path = env::args()
+        .nth(1)
+        .unwrap_or_else(|| DEFAULT_MODULE.to_string());
+    let module = fs::read(&path)
+        .map_err(|error| error.to_string())
+        .and_then(|bytes| parse(&bytes).map(|module| (bytes.len(), module)));
+    let (module_size, module) = match module {
+        Ok(module) => module,
+        Err(error) => {
+            eprintln!("error: {path}: {error}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    report(&module, module_size);
+    let problems = audit(&module);
+    if problems.is_empty() {
+        println!("\nexports and imports match scubywasm_agent.h");
+        ExitCode::SUCCESS
+    } else {
+        for problem in &problems {
+            eprintln!("error: {problem}");
+        }
+        ExitCode::FAILURE
+    }
+}
+
+/// Prints the exports, the imports and the code size per function.
+fn report(module: &Module, module_size: usize) {
+    println!("exports:");
+    for export in &module.exports {
+        match export.kind {
+            Kind::Function => println!(
+                "  function {} {}",
+                export.name,
+                module.signature(export.index).unwrap_or("(unknown type)")
+            ),
+            kind => println!("  {kind} {}", export.name),
+        }
+    }
+    println!("imports:");
+    for import in &module.imports {
+        println!(
+            "  {} {}.{} {}",
+            import.kind,
+            import.module,
+            import.name,
+            import.signature.as_deref().unwrap_or_default()
+        );
+    }
+
+    let imported = module.imported_functions();
+    let mut functions: Vec<_> = module
+        .code_sizes
+        .iter()
+        .enumerate()
+        .map(|(defined, &size)| (size, module.function_name(imported + defined as u32)))
+        .collect();
+    functions.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+    let code_size: usize = functions.iter().map(|(size, _)| size).sum();
+    println!(
+        "code: {code_size} bytes in {} functions, module {module_size} bytes",
+        functions.len()
+    );
+    for (size, name) in functions.iter().take(LISTED_FUNCTIONS) {
+        println!("  {size:>8}  {name}");
+    }
+    if let Some(others) = functions.get(LISTED_FUNCTIONS..).filter(|o| !o.is_empty()) {
+        let size: usize = others.iter().map(|(size, _)| size).sum();
+        println!("  {size:>8}  {} other functions", others.len());
+    }
+    for (label, parts) in [("formatting", FORMATTING), ("panicking", PANICKING)] {
+        let (count, size) = functions
+            .iter()
+            .filter(|(_, name)| parts.iter().any(|part| name.contains(part)))
+            .fold((0, 0), |(count, total), (size, _)| {
+                (count + 1, total + size)
+            });
+        println!("{label}: {size} bytes in {count} functions");
+    }
+}
+
+/// Differences between the module and the functions of the header and the host imports.
+fn audit(module: &Module) -> Vec<String> {
+    let mut problems = Vec::new();
+    for &(name, expected) in EXPORTS {
+        let export = module
+            .exports
+            .iter()
+            .find(|export| export.kind == Kind::Function && export.name == name);
+        match export.map(|export| module.signature(export.index)) {
+            None => problems.push(format!("missing export `{name}`")),
+            Some(signature) if signature != Some(expected) => problems.push(format!(
+                "export `{name}` has the signature {}, the header declares {expected}",
+                signature.unwrap_or("(unknown type)")
+            )),
+            Some(_) => {}
+        }
+    }
+    for export in &module.exports {
+        if export.kind == Kind::Function && !EXPORTS.iter().any(|&(name, _)| name == export.name) {
+            problems.push(format!(
+                "unexpected export `{}`, it is not in the header",
+                export.name
+            ));
+        }
+    }
+
+    for &(module_name, name, expected) in IMPORTS {
+        let import = module
+            .imports
+            .iter()
+            .find(|import| import.module == module_name && import.name == name);
+        match import {
+            None => problems.push(format!("missing import `{module_name}.{name}`")),
+            Some(import) if import.signature.as_deref() != Some(expected) => {
+                problems.push(format!(
+                    "import `{module_name}.{name}` has the signature {}, the host provides {expected}",
+                    import.signature.as_deref().unwrap_or("(no function)")
+                ))
+            }
+            Some(_) => {}
+        }
+    }
+    for import in &module.imports {
+        if !IMPORTS
+            .iter()
+            .any(|&(module, name, _)| import.module == module && import.name == name)
+        {
+            problems.push(format!(
+                "unexpected import `{}.{}`, the host does not provide it",
+                import.module, import.name
+            ));
+        }
+    }
+    problems
+}
+
+/// Parses the sections of a wasm module, the function bodies are only measured.
+fn parse(bytes: &[u8]) -> Result<Module, String> {
+    let mut reader = Reader { bytes, position: 0 };
+    if reader.bytes(4)? != b"\0asm" {
+        return Err("not a wasm module".into());
+    }
+    if reader.bytes(4)? != [1, 0, 0, 0] {
+        return Err("unsupported wasm version".into());
+    }
+    let mut module = Module::default();
+    while !reader.done() {
+        let id = reader.byte()?;
+        let size = reader.u32()? as usize;
+        let mut section = Reader {
+            bytes: reader.bytes(size)?,
+            position: 0,
+        };
+        match id {
+            0 => parse_custom(&mut section, &mut module)?,
+            1 => {
+                for _ in 0..section.u32()? {
+                    let signature = parse_function_type(&mut section)?;
+                    module.types.push(signature);
+                }
+            }
+            2 => {
+                for _ in 0..section.u32()? {
+                    let import = parse_import(&mut section, &module)?;
+                    module.imports.push(import);
+                }
+            }
+            3 => {
+                for _ in 0..section.u32()? {
+                    module.functions.push(section.u32()?);
+                }
+            }
+            7 => {
+                for _ in 0..section.u32()? {
+                    let name = section.name()?;
+                    let kind = parse_kind(&mut section)?;
+                    let index = section.u32()?;
+                    module.exports.push(Export { name, kind, index });
+                }
+            }
+            10 => {
+                for _ in 0..section.u32()? {
+                    let size = section.u32()? as usize;
+                    section.bytes(size)?;
+                    module.code_sizes.push(size);
+                }
+            }
+            // the other sections don't matter for the audit
+            _ => {}
+        }
+    }
+    Ok(module)
+}
+
+/// Reads the function names of the `name` section, the other custom sections are skipped.
+fn parse_custom(section: &mut Reader, module: &mut Module) -> Result<(), String> {
+    if section.name()? != "name" {
+        return Ok(());
+    }
+    while !section.done() {
+        let id = section.byte()?;
+        let size = section.u32()? as usize;
+        let mut subsection = Reader {
+            bytes: section.bytes(size)?,
+            position: 0,
+        };
+        // subsection 1 names the functions
+        if id == 1 {
+            for _ in 0..subsection.u32()? {
+                let index = subsection.u32()?;
+                let name = subsection.name()?;
+                module.names.insert(index, name);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Parses a function type into its signature, like `(i32, f32) -> i32`.
+fn parse_function_type(section: &mut Reader) -> Result<String, String> {
+    if section.byte()? != 0x60 {
+        return Err("unsupported type, only function types are known".into());
+    }
+    let mut value_types = || {
+        (0..section.u32()?)
+            .map(|_| value_type(section.byte()?))
+            .collect::<Result<Vec<_>, _>>()
+    };
+    let parameters = value_types()?;
+    let results = value_types()?;
+    let returned = match results.as_slice() {
+        [] => String::new(),
+        [result] => format!(" -> {result}"),
+        results => format!(" -> ({})", results.join(", ")),
+    };
+    Ok(format!("({}){returned}", parameters.join(", ")))
+}
+
+fn value_type(byte: u8) -> Result<&'static str, String> {
+    match byte {
+        0x7f => Ok("i32"),
+        0x7e => Ok("i64"),
+        0x7d => Ok("f32"),
+        0x7c => Ok("f64"),
+        0x7b => Ok("v128"),
+        0x70 => Ok("funcref"),
+        0x6f => Ok("externref"),
+        _ => Err(format!("unknown value type {byte:#x}")),
+    }
+}
+
+fn parse_kind(section: &mut Reader) -> Result<Kind, String> {
+    match section.byte()? {
+        0 => Ok(Kind::Function),
+        1 => Ok(Kind::Table),
+        2 => Ok(Kind::Memory),
+        3 => Ok(Kind::Global),
+        4 => Ok(Kind::Tag),
+        kind => Err(format!("unknown import or export kind {kind}")),
+    }
+}
+
+fn parse_import(section: &mut Reader, module: &Module) -> Result<Import, String> {
+    let module_name = section.name()?;
+    let name = section.name()?;
+    let kind = parse_kind(section)?;
+    let mut signature = None;
+    match kind {
+        Kind::Function => {
+            let type_index = section.u32()?;
+            let function_type = module
+                .types
+                .get(type_index as usize)
+                .ok_or_else(|| format!("import `{module_name}.{name}` has an unknown type"))?;
+            signature = Some(function_type.clone());
+        }
+        Kind::Table => {
+            section.byte()?;
+            skip_limits(section)?;
+        }
+        Kind::Memory => skip_limits(section)?,
+        Kind::Global => {
+            section.bytes(2)?;
+        }
+        Kind::Tag => {
+            section.byte()?;
+            section.u32()?;
+        }
+    }
+    Ok(Import {
+        module: module_name,
+        name,
+        kind,
+        signature,
+    })
+}
+
+fn skip_limits(section: &mut Reader) -> Result<(), String> {
+    let flags = section.byte()?;
+    section.leb128()?;
+    // bit 0 is set when there is a maximum
+    if flags & 1 != 0 {
+        section.leb128()?;
+    }
+    Ok(())
+}
+
+/// Turns a mangled Rust symbol into its path without the hash, other names are kept. Legacy
+/// symbols like `_ZN4core3fmt5write17h0123456789abcdefE` become `core::fmt::write`, v0 symbols
+/// like `_RNvNtCs1234_4core3fmt5write` as well.
+fn demangle(name: &str) -> String {
+    if let Some(symbol) = name.strip_prefix("_R") {
+        // suffixes like `.llvm.1234` are not part of the mangled name
+        let symbol = symbol.split('.').next().unwrap_or_default();
+        return V0 {
+            bytes: symbol.as_bytes(),
+            position: 0,
+            depth: 0,
+        }
+        .demangle()
+        .unwrap_or_else(|| name.to_string());
+    }
+    let Some(mut rest) = name.strip_prefix("_ZN") else {
+        return name.to_string();
+    };
+    let mut segments = Vec::new();
+    while let Some(digits) = rest.find(|c: char| !c.is_ascii_digit()).filter(|&d| d > 0) {
+        let Ok(length) = rest[..digits].parse::<usize>() else {
+            return name.to_string();
+        };
+        let Some(segment) = rest.get(digits..digits + length) else {
+            return name.to_string();
+        };
+        // segments that start with an escape get a leading underscore
+        segments.push(
+            segment
+                .strip_prefix("_$")
+                .map_or(segment, |_| &segment[1..]),
+        );
+        rest = &rest[digits + length..];
+    }
+    if rest != "E" {
+        return name.to_string();
+    }
+    // the last segment is the hash
+    if segments.last().is_some_and(|segment| {
+        segment.len() == 17
+            && segment.starts_with('h')
+            && segment[1..].chars().all(|c| c.is_ascii_hexdigit())
+    }) {
+        segments.pop();
+    }
+    let mut demangled = segments.join("::");
+    for (escaped, character) in [
+        ("$LT$", "<"),
+        ("$GT$", ">"),
+        ("$RF$", "&"),
+        ("$BP$", "*"),
+        ("$C$", ","),
+        ("$SP$", "@"),
+        ("$u20$", " "),
+        ("$u27$", "'"),
+        ("$u5b$", "["),
+        ("$u5d$", "]"),
+        ("$u7b$", "{"),
+        ("$u7d$", "}"),
+        ("$u7e$", "~"),
+        ("..", "::"),
+    ] {
+        demangled = demangled.replace(escaped, character);
+    }
+    demangled
+}
+
+/// Demangles v0 symbols, see <https://doc.rust-lang.org/rustc/symbol-mangling/v0.html>. The
+/// paths are written like `rustc` displays them, every method returns `None` on a malformed or
+/// unsupported symbol.
+struct V0<'a> {
+    /// the symbol after `_R`, back references are offsets into it
+    bytes: &'a [u8],
+    position: usize,
+    /// back references that are followed right now, bounds the recursion
+    depth: u32,
+}
+
+/// Back references that may be followed inside each other.
+const MAX_BACKREF_DEPTH: u32 = 64;
+
+impl<'a> V0<'a> {
+    fn demangle(mut self) -> Option<String> {
+        // the encoding version is optional
+        while self.peek().is_some_and(|byte| byte.is_ascii_digit()) {
+            self.position += 1;
+        }
+        let mut out = String::new();
+        self.path(&mut out, true)?;
+        // the instantiating crate that may follow is not shown
+        Some(out)
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.position).copied()
+    }
+
+    fn next(&mut self) -> Option<u8> {
+        let byte = self.peek()?;
+        self.position += 1;
+        Some(byte)
+    }
+
+    fn eat(&mut self, byte: u8) -> bool {
+        let eaten = self.peek() == Some(byte);
+        if eaten {
+            self.position += 1;
+        }
+        eaten
+    }
+
+    /// `_` is 0, otherwise the digits `0-9a-zA-Z` up to `_` plus one.
+    fn base62(&mut self) -> Option<u64> {
+        if self.eat(b'_') {
+            return Some(0);
+        }
+        let mut value: u64 = 0;
+        loop {
+            let digit = match self.next()? {
+                digit @ b'0'..=b'9' => digit - b'0',
+                digit @ b'a'..=b'z' => digit - b'a' + 10,
+                digit @ b'A'..=b'Z' => digit - b'A' + 36,
+                b'_' => return value.checked_add(1),
+                _ => return None,
+            };
+            value = value.checked_mul(62)?.checked_add(u64::from(digit))?;
+        }
+    }
+
+    fn decimal(&mut self) -> Option<usize> {
+        let start = self.position;
+        while self.peek().is_some_and(|byte| byte.is_ascii_digit()) {
+            self.position += 1;
+        }
+        core::str::from_utf8(&self.bytes[start..self.position])
+            .ok()?
+            .parse()
+            .ok()
+    }
+
+    fn disambiguator(&mut self) -> Option<u64> {
+        if self.eat(b's') {
+            self.base62()?.checked_add(1)
+        } else {
+            Some(0)
+        }
+    }
+
+    /// Identifier without its disambiguator, punycode identifiers are kept encoded.
+    fn undisambiguated_identifier(&mut self) -> Option<&'a str> {
+        self.eat(b'u');
+        let length = self.decimal()?;
+        // separates the length from identifiers that start with a digit or `_`
+        self.eat(b'_');
+        let end = self.position.checked_add(length)?;
+        let identifier = core::str::from_utf8(self.bytes.get(self.position..end)?).ok()?;
+        self.position = end;
+        Some(identifier)
+    }
+
+    /// Follows the back reference at the current position with `parse`, then continues after
+    /// it.
+    fn backref(&mut self, parse: impl FnOnce(&mut Self) -> Option<()>) -> Option<()> {
+        // the reference starts at the `B` that was just read
+        let start = self.position - 1;
+        let target = usize::try_from(self.base62()?).ok()?;
+        // references only point backwards, which ends every chain of them
+        if target >= start || self.depth >= MAX_BACKREF_DEPTH {
+            return None;
+        }
+        let position = core::mem::replace(&mut self.position, target);
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+        self.position = position;
+        result
+    }
+
+    /// Writes the path, generic arguments of values get a `::` before them.
+    fn path(&mut self, out: &mut String, value: bool) -> Option<()> {
+        match self.next()? {
+            b'C' => {
+                self.disambiguator()?;
+                out.push_str(self.undisambiguated_identifier()?);
+            }
+            b'N' => {
+                let namespace = self.next()?;
+                self.path(out, value)?;
+                let disambiguator = self.disambiguator()?;
+                let identifier = self.undisambiguated_identifier()?;
+                out.push_str("::");
+                match namespace {
+                    b'a'..=b'z' => out.push_str(identifier),
+                    _ => {
+                        let kind = match namespace {
+                            b'C' => "closure".to_string(),
+                            b'S' => "shim".to_string(),
+                            other => char::from(other).to_string(),
+                        };
+                        out.push_str(&format!("{{{kind}"));
+                        if !identifier.is_empty() {
+                            out.push_str(&format!(":{identifier}"));
+                        }
+                        out.push_str(&format!("#{disambiguator}}}"));
+                    }
+                }
+            }
+            // inherent impl
+            b'M' => {
+                self.impl_path()?;
+                out.push('<');
+                self.ty(out)?;
+                out.push('>');
+            }
+            // trait impl
+            b'X' => {
+                self.impl_path()?;
+                out.push('<');
+                self.ty(out)?;
+                out.push_str(" as ");
+                self.path(out, false)?;
+                out.push('>');
+            }
+            // trait definition
+            b'Y' => {
+                out.push('<');
+                self.ty(out)?;
+                out.push_str(" as ");
+                self.path(out, false)?;
+                out.push('>');
+            }
+            b'I' => {
+                self.path(out, value)?;
+                if value {
+                    out.push_str("::");
+                }
+                out.push('<');
+                let mut first = true;
+                while !self.eat(b'E') {
+                    if !first {
+                        out.push_str(", ");
+                    }
+                    first = false;
+                    self.generic_arg(out)?;
+                }
+                out.push('>');
+            }
+            b'B' => self.backref(|v0| v0.path(out, value))?,
+            _ => return None,
+        }
+        Some(())
+    }
+
+    /// Path of the module that contains an impl, it is not shown.
+    fn impl_path(&mut self) -> Option<()> {
+        self.disambiguator()?;
+        self.path(&mut String::new(), false)
+    }
+
+    fn generic_arg(&mut self, out: &mut String) -> Option<()> {
+        if self.eat(b'L') {
+            self.base62()?;
+            out.push_str("'_");
+        } else if self.eat(b'K') {
+            self.constant(out)?;
+        } else {
+            self.ty(out)?;
+        }
+        Some(())
+    }
+
+    fn constant(&mut self, out: &mut String) -> Option<()> {
+        if self.eat(b'p') {
+            out.push('_');
+            return Some(());
+        }
+        if self.eat(b'B') {
+            return self.backref(|v0| v0.constant(out));
+        }
+        let ty = self.next()?;
+        let negative = self.eat(b'n');
+        let start = self.position;
+        while self.peek().is_some_and(|byte| byte.is_ascii_hexdigit()) {
+            self.position += 1;
+        }
+        let digits = core::str::from_utf8(&self.bytes[start..self.position]).ok()?;
+        if !self.eat(b'_') {
+            return None;
+        }
+        let value = if digits.is_empty() {
+            0
+        } else {
+            u128::from_str_radix(digits, 16).ok()?
+        };
+        match ty {
+            b'b' => out.push_str(if value == 0 { "false" } else { "true" }),
+            b'c' => out.push(char::from_u32(u32::try_from(value).ok()?)?),
+            _ => {
+                if negative {
+                    out.push('-');
+                }
+                out.push_str(&value.to_string());
+            }
+        }
+        Some(())
+    }
+
+    fn ty(&mut self, out: &mut String) -> Option<()> {
+        let basic = match self.peek()? {
+            b'a' => "i8",
+            b'b' => "bool",
+            b'c' => "char",
+            b'd' => "f64",
+            b'e' => "str",
+            b'f' => "f32",
+            b'h' => "u8",
+            b'i' => "isize",
+            b'j' => "usize",
+            b'l' => "i32",
+            b'm' => "u32",
+            b'n' => "i128",
+            b'o' => "u128",
+            b's' => "i16",
+            b't' => "u16",
+            b'u' => "()",
+            b'v' => "...",
+            b'x' => "i64",
+            b'y' => "u64",
+            b'z' => "!",
+            b'p' => "_",
+            _ => "",
+        };
+        if !basic.is_empty() {
+            self.position += 1;
+            out.push_str(basic);
+            return Some(());
+        }
+        match self.peek()? {
+            b'C' | b'N' | b'M' | b'X' | b'Y' | b'I' => return self.path(out, false),
+            _ => {}
+        }
+        match self.next()? {
+            tag @ (b'R' | b'Q') => {
+                if self.eat(b'L') {
+                    self.base62()?;
+                }
+                out.push_str(if tag == b'R' { "&" } else { "&mut " });
+                self.ty(out)?;
+            }
+            tag @ (b'P' | b'O') => {
+                out.push_str(if tag == b'P' { "*const " } else { "*mut " });
+                self.ty(out)?;
+            }
+            b'A' => {
+                out.push('[');
+                self.ty(out)?;
+                out.push_str("; ");
+                self.constant(out)?;
+                out.push(']');
+            }
+            b'S' => {
+                out.push('[');
+                self.ty(out)?;
+                out.push(']');
+            }
+            b'T' => {
+                out.push('(');
+                let mut count = 0;
+                while !self.eat(b'E') {
+                    if count > 0 {
+                        out.push_str(", ");
+                    }
+                    self.ty(out)?;
+                    count += 1;
+                }
+                if count == 1 {
+                    out.push(',');
+                }
+                out.push(')');
+            }
+            b'F' => self.fn_sig(out)?,
+            b'D' => {
+                if self.eat(b'G') {
+                    self.base62()?;
+                }
+                out.push_str("dyn ");
+                let mut first = true;
+                while !self.eat(b'E') {
+                    if !first {
+                        out.push_str(" + ");
+                    }
+                    first = false;
+                    self.path(out, false)?;
+                    // associated type bindings like `Item = u8`
+                    let mut bindings = 0;
+                    while self.eat(b'p') {
+                        out.push_str(if bindings == 0 { "<" } else { ", " });
+                        out.push_str(self.undisambiguated_identifier()?);
+                        out.push_str(" = ");
+                        self.ty(out)?;
+                        bindings += 1;
+                    }
+                    if bindings > 0 {
+                        out.push('>');
+                    }
+                }
+                if !self.eat(b'L') {
+                    return None;
+                }
+                self.base62()?;
+            }
+            b'B' => self.backref(|v0| v0.ty(out))?,
+            _ => return None,
+        }
+        Some(())
+    }
+
+    fn fn_sig(&mut self, out: &mut String) -> Option<()> {
+        if self.eat(b'G') {
+            self.base62()?;
+        }
+        if self.eat(b'U') {
+            out.push_str("unsafe ");
+        }
+        if self.eat(b'K') {
+            let abi = if self.eat(b'C') {
+                "C"
+            } else {
+                self.undisambiguated_identifier()?
+            };
+            out.push_str(&format!("extern \"{abi}\" "));
+        }
+        out.push_str("fn(");
+        let mut first = true;
+        while !self.eat(b'E') {
+            if !first {
+                out.push_str(", ");
+            }
+            first = false;
+            self.ty(out)?;
+        }
+        out.push(')');
+        let mut output = String::new();
+        self.ty(&mut output)?;
+        if output != "()" {
+            out.push_str(" -> ");
+            out.push_str(&output);
+        }
+        Some(())
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn done(&self) -> bool {
+        self.position >= self.bytes.len()
+    }
+
+    fn byte(&mut self) -> Result<u8, String> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn bytes(&mut self, count: usize) -> Result<&'a [u8], String> {
+        let bytes = self
+            .position
+            .checked_add(count)
+            .and_then(|end| self.bytes.get(self.position..end))
+            .ok_or_else(|| format!("unexpected end at byte {}", self.position))?;
+        self.position += count;
+        Ok(bytes)
+    }
+
+    /// Reads an unsigned LEB128 number.
+    fn leb128(&mut self) -> Result<u64, String> {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(format!("number too long at byte {}", self.position))
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        let value = self.leb128()?;
+        u32::try_from(value).map_err(|_| format!("{value} does not fit into 32 bits"))
+    }
+
+    fn name(&mut self) -> Result<String, String> {
+        let length = self.u32()? as usize;
+        let bytes = self.bytes(length)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| "name is not UTF-8".into())
+    }
+}
@@ -0,0 +1,46 @@
+use core::fmt::{Display, Formatter};
+
+/// Work units one call of `make_action` may spend when nothing else is configured. One unit is
+/// roughly one simulated tick of one ship.
+pub const DEFAULT_BUDGET: u32 = 20_000;
+
+/// Compute budget of one decision. Wall clocks may be unavailable in wasm, so the work is
+/// counted in units instead of time. Every planner spends from it before doing expensive work
+/// and stops with the best result found so far once it is exhausted.
+#[derive(Clone, Copy)]
+pub struct Budget {
+    limit: u32,
+    spent: u32,
+}
+
+impl Budget {
+    pub fn new(limit: u32) -> Self {
+        Self { limit, spent: 0 }
+    }
+
+    /// Spends `cost` units if that many are left. Otherwise the budget counts as exhausted, so
+    /// that cheaper work after the refused one is skipped as well.
+    pub fn try_spend(&mut self, cost: u32) -> bool {
+        if cost > self.remaining() {
+            self.spent = self.limit;
+            return false;
+        }
+        self.spent += cost;
+        true
+    }
+
+    pub fn remaining(&self) -> u32 {
+        self.limit - self.spent
+    }
+
+    /// Whether a planner had to stop early because the budget ran out.
+    pub fn exhausted(&self) -> bool {
+        self.spent >= self.limit
+    }
+}
+
+impl Display for Budget {
+    fn fmt(&self, formatter: &mut Formatter) -> Result<(), core::fmt::Error> {
+        write!(formatter, "{}/{} units", self.spent, self.limit)
+    }
+}
@@ -0,0 +1,108 @@
+use crate::{
+    Ship,
+    kinematics::{KinematicState, Kinematics},
+    spatial,
+};
+
+/// Number of ticks for which ship collisions are predicted.
+pub const COLLISION_HORIZON: u32 = 20;
+
+/// Distance that is kept to allies, in hit radii, so that they don't clump together and can't be
+/// hit by the same shot.
+const ALLY_SPACING_RADII: f32 = 4.0;
+
+/// Additional distance that is kept to enemies on top of the touching distance.
+const ENEMY_MARGIN: f32 = 0.02;
+
+/// Smallest margin between our ship and any other ship while repeating `action` for the next
+/// [`COLLISION_HORIZON`] ticks. Other ships are assumed to keep their velocity. Distances are
+/// measured across the playfield edges. The margin is negative when a ship gets closer than the
+/// distance that should be kept to it.
+pub fn collision_margin(
+    kinematics: &Kinematics,
+    own: &KinematicState,
+    own_agent_id: u32,
+    ships: &[Ship],
+    action: u32,
+    hit_radius: f32,
+) -> f32 {
+    let mut path = [KinematicState::default(); COLLISION_HORIZON as usize];
+    let actions = core::iter::repeat_n(action, path.len());
+    for (state, simulated) in path.iter_mut().zip(kinematics.simulate(own, actions)) {
+        *state = simulated;
+    }
+    ships
+        .iter()
+        .filter(|ship| ship.agent_id != own_agent_id)
+        .map(|ship| {
+            let keep = if ship.friendly {
+                ALLY_SPACING_RADII * hit_radius
+            } else {
+                2.0 * hit_radius + ENEMY_MARGIN
+            };
+            path.iter()
+                .zip(1..)
+                .map(|(state, tick)| {
+                    let ship_x = ship.pos_x + ship.vel_x * tick as f32;
+                    let ship_y = ship.pos_y + ship.vel_y * tick as f32;
+                    spatial::wrapped_distance((state.pos_x, state.pos_y), (ship_x, ship_y)) - keep
+                })
+                .fold(f32::INFINITY, f32::min)
+        })
+        .fold(f32::INFINITY, f32::min)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bindings;
+
+    const KINEMATICS: Kinematics = Kinematics {
+        turn_rate: 0.1,
+        max_velocity: 0.005,
+        thrust_acceleration: 0.005,
+        drag: 1.0,
+    };
+
+    const HIT_RADIUS: f32 = 0.02;
+
+    fn enemy(pos_x: f32, pos_y: f32) -> Ship {
+        Ship {
+            agent_id: 1,
+            hp: 3,
+            pos_x,
+            pos_y,
+            ..Ship::default()
+        }
+    }
+
+    fn margin(own: &KinematicState, ships: &[Ship]) -> f32 {
+        let thrust = bindings::ActionFlags_ACTION_THRUST;
+        collision_margin(&KINEMATICS, own, 0, ships, thrust, HIT_RADIUS)
+    }
+
+    #[test]
+    fn collision_across_the_edge_is_detected() {
+        // flying along the x axis towards the right edge, the enemy waits behind it
+        let own = KinematicState {
+            pos_x: 0.99,
+            pos_y: 0.5,
+            ..KinematicState::default()
+        };
+        assert!(margin(&own, &[enemy(0.02, 0.5)]) < 0.0);
+    }
+
+    #[test]
+    fn ship_behind_the_edge_is_not_too_close() {
+        // flying away from the edge, the enemy on the other side stays behind
+        let own = KinematicState {
+            pos_x: 0.1,
+            pos_y: 0.5,
+            ..KinematicState::default()
+        };
+        let margin = margin(&own, &[enemy(0.95, 0.5)]);
+        // the closest point is after the first tick
+        let distance = 0.155 - (2.0 * HIT_RADIUS + ENEMY_MARGIN);
+        assert!((margin - distance).abs() < 1e-4, "{margin}");
+    }
+}
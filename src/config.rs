@@ -1,3 +1,5 @@
+use crate::bindings;
+
 #[derive(Default)]
 pub struct Config {
     pub ship_max_turn_rate: f32,
@@ -8,13 +10,17 @@ pub struct Config {
 }
 
 impl Config {
-    pub fn update(&mut self, id: u32, value: f32) {
+    pub fn update(&mut self, id: bindings::ConfigParameter, value: f32) {
+        // none of the parameters can be negative, broken values keep the previous one
+        if !value.is_finite() || value < 0.0 {
+            return;
+        }
         match id {
-            0 => self.ship_max_turn_rate = value,
-            1 => self.ship_max_velocity = value,
-            2 => self.ship_hit_radius = value,
-            3 => self.shot_velocity = value,
-            4 => self.shot_lifetime = value,
+            bindings::ConfigParameter_CFG_SHIP_MAX_TURN_RATE => self.ship_max_turn_rate = value,
+            bindings::ConfigParameter_CFG_SHIP_MAX_VELOCITY => self.ship_max_velocity = value,
+            bindings::ConfigParameter_CFG_SHIP_HIT_RADIUS => self.ship_hit_radius = value,
+            bindings::ConfigParameter_CFG_SHOT_VELOCITY => self.shot_velocity = value,
+            bindings::ConfigParameter_CFG_SHOT_LIFETIME => self.shot_lifetime = value,
             _ => (),
         }
     }
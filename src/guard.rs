@@ -0,0 +1,64 @@
+#[cfg(not(target_arch = "wasm32"))]
+use std::{
+    panic::{self, AssertUnwindSafe},
+    sync::Once,
+};
+
+use crate::log;
+
+/// Logs every panic through `debug_log` before it unwinds, with the location that the payload
+/// lacks. The previous hook still runs after it.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn install_panic_hook() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        let previous_hook = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            let message = info
+                .payload()
+                .downcast_ref::<&str>()
+                .copied()
+                .or_else(|| info.payload().downcast_ref::<String>().map(String::as_str))
+                .unwrap_or("unknown panic");
+            match info.location() {
+                Some(location) => log!("panicked at {location}: {message}"),
+                None => log!("panicked: {message}"),
+            }
+            previous_hook(info);
+        }));
+    });
+}
+
+/// Without std there are no panic hooks, the panic handler logs instead.
+#[cfg(target_arch = "wasm32")]
+pub fn install_panic_hook() {}
+
+/// Runs `f` for the export `export` and returns its result, or `None` when it panicked.
+///
+/// The state `f` touched is asserted to be unwind safe, the caller must not trust it afterwards.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn catch_panic<T>(export: &str, f: impl FnOnce() -> T) -> Option<T> {
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(result) => Some(result),
+        Err(_) => {
+            log!("{export} panicked");
+            None
+        }
+    }
+}
+
+/// Panics can't unwind on wasm32, there is nothing to catch. Deciding therefore only reaches the
+/// world state through checked lookups, so that no input of the host can make it panic.
+#[cfg(target_arch = "wasm32")]
+pub fn catch_panic<T>(_export: &str, f: impl FnOnce() -> T) -> Option<T> {
+    Some(f())
+}
+
+/// Logs the panic through `debug_log`, it is the last thing the host receives before the
+/// instance traps.
+#[cfg(target_arch = "wasm32")]
+#[panic_handler]
+fn panic(info: &core::panic::PanicInfo) -> ! {
+    log!("{info}");
+    core::arch::wasm32::unreachable()
+}
@@ -0,0 +1,159 @@
+use crate::prelude::*;
+
+/// Map from agent ids to values, sorted by id in a single buffer.
+///
+/// Unlike the hash maps of std it works without std, needs no random state and keeps its buffer
+/// when it is cleared, so that refilling it every tick does not allocate. Ids mostly arrive in
+/// ascending order, which appends to the end.
+pub struct IdMap<V> {
+    entries: Vec<(u32, V)>,
+}
+
+impl<V> Default for IdMap<V> {
+    fn default() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+}
+
+impl<V> IdMap<V> {
+    /// Index of `id`, or the index where it would have to be inserted.
+    fn search(&self, id: u32) -> Result<usize, usize> {
+        match self.entries.last() {
+            Some(&(last, _)) if last < id => Err(self.entries.len()),
+            None => Err(0),
+            Some(_) => self.entries.binary_search_by_key(&id, |&(key, _)| key),
+        }
+    }
+
+    pub fn get(&self, id: u32) -> Option<&V> {
+        let index = self.search(id).ok()?;
+        Some(&self.entries[index].1)
+    }
+
+    pub fn get_mut(&mut self, id: u32) -> Option<&mut V> {
+        let index = self.search(id).ok()?;
+        Some(&mut self.entries[index].1)
+    }
+
+    /// Value of `id`, inserted with `default` when there is none.
+    pub fn get_or_insert_with(&mut self, id: u32, default: impl FnOnce() -> V) -> &mut V {
+        let index = match self.search(id) {
+            Ok(index) => index,
+            Err(index) => {
+                self.entries.insert(index, (id, default()));
+                index
+            }
+        };
+        &mut self.entries[index].1
+    }
+
+    /// Sets the value of `id` and returns the previous one.
+    pub fn insert(&mut self, id: u32, value: V) -> Option<V> {
+        match self.search(id) {
+            Ok(index) => Some(core::mem::replace(&mut self.entries[index].1, value)),
+            Err(index) => {
+                self.entries.insert(index, (id, value));
+                None
+            }
+        }
+    }
+
+    pub fn remove(&mut self, id: u32) -> Option<V> {
+        let index = self.search(id).ok()?;
+        Some(self.entries.remove(index).1)
+    }
+
+    /// Removes all entries but keeps the buffer.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+/// Set of agent ids, see [`IdMap`].
+#[derive(Default)]
+pub struct IdSet {
+    ids: IdMap<()>,
+}
+
+impl IdSet {
+    pub fn contains(&self, id: u32) -> bool {
+        self.ids.get(id).is_some()
+    }
+
+    /// Adds `id`, returns whether it was not in the set yet.
+    pub fn insert(&mut self, id: u32) -> bool {
+        self.ids.insert(id, ()).is_none()
+    }
+
+    /// Adds all `ids`, sorts once instead of inserting them one by one.
+    pub fn extend(&mut self, ids: impl IntoIterator<Item = u32>) {
+        let entries = &mut self.ids.entries;
+        entries.extend(ids.into_iter().map(|id| (id, ())));
+        entries.sort_unstable_by_key(|&(id, _)| id);
+        entries.dedup_by_key(|&mut (id, _)| id);
+    }
+
+    /// Removes all ids but keeps the buffer.
+    pub fn clear(&mut self) {
+        self.ids.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_are_found_in_any_insertion_order() {
+        let mut map = IdMap::default();
+        for id in [5, 1, 9, 3, 7] {
+            assert_eq!(map.insert(id, id * 10), None);
+        }
+        for id in [1, 3, 5, 7, 9] {
+            assert_eq!(map.get(id), Some(&(id * 10)));
+        }
+        assert_eq!(map.get(4), None);
+        assert_eq!(map.get(10), None);
+        let ids: Vec<_> = map.entries.iter().map(|&(id, _)| id).collect();
+        assert_eq!(ids, [1, 3, 5, 7, 9]);
+    }
+
+    #[test]
+    fn values_are_replaced_and_removed() {
+        let mut map = IdMap::default();
+        map.insert(2, "a");
+        assert_eq!(map.insert(2, "b"), Some("a"));
+        *map.get_or_insert_with(2, || "c") = "d";
+        assert_eq!(map.get(2), Some(&"d"));
+        assert_eq!(*map.get_or_insert_with(1, || "e"), "e");
+        assert_eq!(map.remove(2), Some("d"));
+        assert_eq!(map.remove(2), None);
+        assert_eq!(map.get(1), Some(&"e"));
+    }
+
+    #[test]
+    fn clearing_keeps_the_buffer() {
+        let mut map = IdMap::default();
+        for id in 0..16 {
+            map.insert(id, ());
+        }
+        let capacity = map.entries.capacity();
+        map.clear();
+        assert_eq!(map.get(0), None);
+        assert_eq!(map.entries.capacity(), capacity);
+    }
+
+    #[test]
+    fn set_extends_sorted_without_duplicates() {
+        let mut set = IdSet::default();
+        assert!(set.insert(4));
+        assert!(!set.insert(4));
+        set.extend([8, 2, 4, 2, 6]);
+        let ids: Vec<_> = set.ids.entries.iter().map(|&(id, _)| id).collect();
+        assert_eq!(ids, [2, 4, 6, 8]);
+        assert!(set.contains(6));
+        assert!(!set.contains(5));
+    }
+}
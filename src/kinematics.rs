@@ -0,0 +1,232 @@
+#[cfg(target_arch = "wasm32")]
+use crate::math::Float;
+use crate::{Ship, Shot, bindings, config::Config, numeric, physics::PhysicsEstimator, spatial};
+
+/// Maximum number of ticks a prediction looks ahead, longer predictions are too inaccurate to
+/// be useful.
+pub const MAX_HORIZON: u32 = 64;
+
+/// A shot passing a ship closer than this many hit radii counts as a threat to it.
+const THREAT_RADII: f32 = 3.0;
+
+/// Position, heading and velocity of a ship at one tick.
+#[derive(Default, Clone, Copy)]
+pub struct KinematicState {
+    pub pos_x: f32,
+    pub pos_y: f32,
+    /// heading in radians, 0 points along the x axis and angles grow counter clockwise
+    pub heading: f32,
+    pub vel_x: f32,
+    pub vel_y: f32,
+}
+
+impl From<&Ship> for KinematicState {
+    fn from(ship: &Ship) -> Self {
+        Self {
+            pos_x: ship.pos_x,
+            pos_y: ship.pos_y,
+            heading: ship.heading,
+            vel_x: ship.vel_x,
+            vel_y: ship.vel_y,
+        }
+    }
+}
+
+/// Model of how a ship moves under the different action flags.
+///
+/// Every tick the ship first turns, then its velocity decays by the drag and the thrust is
+/// applied along the new heading, then it moves by its velocity and wraps around the playfield
+/// edges.
+#[derive(Clone, Copy)]
+pub struct Kinematics {
+    /// radians per tick
+    pub turn_rate: f32,
+    pub max_velocity: f32,
+    pub thrust_acceleration: f32,
+    pub drag: f32,
+}
+
+impl Kinematics {
+    /// Creates the model from the configured values, replaced by the measured ones where enough
+    /// observations are available.
+    pub fn new(config: &Config, physics: &PhysicsEstimator) -> Self {
+        Self {
+            turn_rate: physics.ship_turn_rate(config),
+            max_velocity: physics.ship_max_velocity(config),
+            thrust_acceleration: physics.thrust_acceleration(config),
+            drag: physics.drag(),
+        }
+    }
+
+    /// Predicts the state of the ship one tick after `action` was applied.
+    pub fn step(&self, state: &KinematicState, action: u32) -> KinematicState {
+        let mut next = *state;
+        if action & bindings::ActionFlags_ACTION_TURN_LEFT != 0 {
+            next.heading += self.turn_rate;
+        }
+        if action & bindings::ActionFlags_ACTION_TURN_RIGHT != 0 {
+            next.heading -= self.turn_rate;
+        }
+
+        let retained = 1.0 - self.drag;
+        next.vel_x *= retained;
+        next.vel_y *= retained;
+        if action & bindings::ActionFlags_ACTION_THRUST != 0 {
+            next.vel_x += self.thrust_acceleration * next.heading.cos();
+            next.vel_y += self.thrust_acceleration * next.heading.sin();
+        }
+        let speed = (next.vel_x.powi(2) + next.vel_y.powi(2)).sqrt();
+        if speed > self.max_velocity && speed > 0.0 {
+            next.vel_x *= self.max_velocity / speed;
+            next.vel_y *= self.max_velocity / speed;
+        }
+
+        next.pos_x = (next.pos_x + next.vel_x).rem_euclid(spatial::PLAYFIELD_SIZE);
+        next.pos_y = (next.pos_y + next.vel_y).rem_euclid(spatial::PLAYFIELD_SIZE);
+        next
+    }
+
+    /// Predicts the states of the ship after each action of `actions` was applied in order.
+    pub fn simulate(
+        &self,
+        state: &KinematicState,
+        actions: impl IntoIterator<Item = u32>,
+    ) -> impl Iterator<Item = KinematicState> {
+        actions.into_iter().scan(*state, |current, action| {
+            *current = self.step(current, action);
+            Some(*current)
+        })
+    }
+
+    /// Smallest distance between the ship and a shot while the ship repeats `action` until the
+    /// shot expires, measured across the playfield edges. `shot_path` holds the positions of the
+    /// shot, one per tick starting with the current one.
+    pub fn clearance(&self, ship: &KinematicState, action: u32, shot_path: &[(f32, f32)]) -> f32 {
+        let Some((start, path)) = shot_path.split_first() else {
+            return f32::INFINITY;
+        };
+        let actions = core::iter::repeat_n(action, path.len());
+        self.simulate(ship, actions)
+            .zip(path)
+            .map(|(state, shot)| spatial::wrapped_distance((state.pos_x, state.pos_y), *shot))
+            .fold(
+                spatial::wrapped_distance((ship.pos_x, ship.pos_y), *start),
+                f32::min,
+            )
+    }
+}
+
+/// Position of `shot` after `ticks` ticks, shots fly straight with constant velocity and wrap
+/// around the playfield edges.
+pub fn shot_position(shot: &Shot, shot_velocity: f32, ticks: u32) -> (f32, f32) {
+    let travelled = shot_velocity * ticks as f32;
+    (
+        (shot.pos_x + travelled * shot.heading.cos()).rem_euclid(spatial::PLAYFIELD_SIZE),
+        (shot.pos_y + travelled * shot.heading.sin()).rem_euclid(spatial::PLAYFIELD_SIZE),
+    )
+}
+
+/// Whether one of `shots` flying straight passes `ship` closer than [`THREAT_RADII`] hit radii,
+/// measured across the playfield edges.
+pub fn threatened_by<'a>(
+    ship: &Ship,
+    shots: impl IntoIterator<Item = &'a Shot>,
+    hit_radius: f32,
+) -> bool {
+    let radius = hit_radius * THREAT_RADII;
+    shots.into_iter().any(|shot| {
+        let offset = spatial::wrapped_delta((shot.pos_x, shot.pos_y), (ship.pos_x, ship.pos_y));
+        // ships behind the shot are safe
+        numeric::forward_distance(offset, shot.heading) >= 0.0
+            && numeric::lateral_distance(offset, shot.heading) <= radius
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ship(pos_x: f32, pos_y: f32) -> Ship {
+        Ship {
+            agent_id: 1,
+            hp: 3,
+            pos_x,
+            pos_y,
+            ..Ship::default()
+        }
+    }
+
+    fn shot(pos_x: f32, pos_y: f32, heading: f32) -> Shot {
+        Shot {
+            agent_id: 0,
+            lifetime: 10,
+            pos_x,
+            pos_y,
+            heading,
+        }
+    }
+
+    const KINEMATICS: Kinematics = Kinematics {
+        turn_rate: 0.1,
+        max_velocity: 0.005,
+        thrust_acceleration: 0.005,
+        drag: 1.0,
+    };
+
+    #[test]
+    fn step_wraps_around_the_edge() {
+        let state = KinematicState {
+            pos_x: 0.998,
+            pos_y: 0.001,
+            heading: -core::f32::consts::FRAC_PI_4,
+            ..KinematicState::default()
+        };
+        let next = KINEMATICS.step(&state, bindings::ActionFlags_ACTION_THRUST);
+        assert!((0.0..0.01).contains(&next.pos_x), "{}", next.pos_x);
+        assert!((0.99..1.0).contains(&next.pos_y), "{}", next.pos_y);
+    }
+
+    #[test]
+    fn step_turns_and_thrusts_along_the_new_heading() {
+        let state = KinematicState {
+            pos_x: 0.5,
+            pos_y: 0.5,
+            ..KinematicState::default()
+        };
+        let action = bindings::ActionFlags_ACTION_THRUST | bindings::ActionFlags_ACTION_TURN_LEFT;
+        let next = KINEMATICS.step(&state, action);
+        assert!((next.heading - 0.1).abs() < 1e-6);
+        assert!((next.vel_x - 0.005 * 0.1f32.cos()).abs() < 1e-6);
+        assert!((next.vel_y - 0.005 * 0.1f32.sin()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn clearance_is_measured_across_the_edge() {
+        let state = KinematicState {
+            pos_x: 0.01,
+            pos_y: 0.5,
+            ..KinematicState::default()
+        };
+        let shot = shot(0.97, 0.5, 0.0);
+        let path: Vec<_> = (0..10)
+            .map(|tick| shot_position(&shot, 0.01, tick))
+            .collect();
+        // the shot catches up with the ship that flies away from it
+        let clearance = KINEMATICS.clearance(&state, bindings::ActionFlags_ACTION_THRUST, &path);
+        assert!(clearance < 0.04, "{clearance}");
+        assert!(path.iter().all(|(x, _)| (0.0..1.0).contains(x)));
+    }
+
+    #[test]
+    fn shot_across_the_edge_threatens() {
+        let shots = [shot(0.95, 0.5, 0.0)];
+        assert!(threatened_by(&ship(0.05, 0.52), &shots, 0.02));
+    }
+
+    #[test]
+    fn shot_flying_away_does_not_threaten() {
+        let shots = [shot(0.05, 0.5, 0.0)];
+        assert!(!threatened_by(&ship(0.95, 0.5), &shots, 0.02));
+        assert!(!threatened_by(&ship(0.3, 0.7), &shots, 0.02));
+    }
+}
@@ -1,43 +1,214 @@
-use std::{
-    cmp::Ordering,
-    collections::HashSet,
-    fmt::{Display, Formatter},
-};
+#![cfg_attr(target_arch = "wasm32", no_std)]
 
-use config::Config;
+extern crate alloc;
+
+use core::fmt::{Display, Formatter};
 
+use action::ActionBuilder;
+use analysis::TickAnalysis;
+use behavior_tree::Node;
+use behaviors::{Blackboard, Scratch};
+use budget::Budget;
+use config::Config;
+use id_map::{IdMap, IdSet};
+use kinematics::Kinematics;
+use logging::Listed;
+#[cfg(not(target_arch = "wasm32"))]
+pub use logging::{log_to_stderr, set_native_log};
+use mcts::Planner;
+use opponents::OpponentModels;
+use physics::PhysicsEstimator;
+use prediction::ActionPredictor;
+use prelude::*;
+use safe_mode::{SafeMode, Trigger};
+use state_machine::ModeMachine;
+pub use state_machine::{ShipMode, Transition};
+
+mod action;
+#[cfg(any(target_arch = "wasm32", test))]
+mod allocator;
+mod analysis;
+mod behavior_tree;
+mod behaviors;
 mod bindings;
+mod budget;
+mod collision;
 mod config;
+mod guard;
+mod id_map;
+mod kinematics;
+mod line_of_fire;
 mod logging;
+#[cfg(target_arch = "wasm32")]
+mod math;
+mod mcts;
+mod numeric;
+mod opponents;
+mod physics;
+mod prediction;
+mod prelude;
+mod safe_mode;
+mod spatial;
+mod state_machine;
+mod utility;
 
 #[derive(Default)]
 pub struct Context {
     config: Config,
-    _seed: u32,
     world_state: WorldState,
-    /// stores ships that are owned by this agent that did not yet receive instructions on what to do next
-    own_ships_to_action: Vec<Ship>,
+    /// stores indices into the ships of the world state of ships that are owned by this agent that did not yet receive instructions on what to do next
+    own_ships_to_action: Vec<usize>,
     /// Agent ids of ships that are in this team.
-    own_agent_ids: HashSet<u32>,
+    own_agent_ids: IdSet,
+    /// Physics of the host measured from consecutive world states.
+    physics: PhysicsEstimator,
+    /// Behavior profiles of the enemy agents.
+    opponents: OpponentModels,
+    /// Predicted next actions of the enemy agents.
+    predictor: ActionPredictor,
+    aim_mode: AimMode,
+    decision_mode: DecisionMode,
+    /// Behavior tree of each own agent, keeps the running state of the nodes between ticks.
+    behavior_trees: IdMap<Node>,
+    /// Mode of each own ship, with its transitions.
+    ship_modes: IdMap<ModeMachine>,
+    /// Simulates futures of the match to pick actions, its random numbers are seeded by the host.
+    planner: Planner,
+    /// Work units each call of `make_action` may spend on its decision.
+    compute_budget: u32,
+    /// Analysis of the current tick shared by the decisions of all own agents, built on the first
+    /// call of `make_action` after the world state was cleared.
+    analysis: TickAnalysis,
+    /// Buffers of the blackboard, kept so that deciding does not allocate.
+    scratch: Scratch,
+    /// Set once a panic was caught in an export.
+    degraded: bool,
+    /// Whether the backup policy decides instead of the main strategy.
+    safe_mode: SafeMode,
+}
+
+impl Context {
+    /// Whether a panic was caught in one of the exports.
+    pub fn degraded(&self) -> bool {
+        self.degraded
+    }
+
+    /// Whether the last decision was made by the backup policy of safe mode.
+    pub fn in_safe_mode(&self) -> bool {
+        self.safe_mode.active()
+    }
+
+    /// Enters safe mode on `tick`, as if the main strategy had misbehaved.
+    pub fn enter_safe_mode(&mut self, tick: u32) {
+        self.safe_mode.enter(tick);
+    }
+
+    /// Decision layer the agent was built with.
+    pub fn decision_mode(&self) -> DecisionMode {
+        self.decision_mode
+    }
+
+    /// Sets the work units each call of `make_action` may spend on its decision.
+    pub fn set_compute_budget(&mut self, units: u32) {
+        self.compute_budget = units;
+    }
+
+    /// Mode of the ship of `agent_id`, `None` before the behavior tree decided for it.
+    pub fn ship_mode(&self, agent_id: u32) -> Option<ShipMode> {
+        self.ship_modes.get(agent_id).map(ModeMachine::mode)
+    }
+
+    /// The last mode transitions of the ship of `agent_id`, oldest first.
+    pub fn mode_transitions(&self, agent_id: u32) -> impl Iterator<Item = &Transition> {
+        self.ship_modes
+            .get(agent_id)
+            .into_iter()
+            .flat_map(ModeMachine::transitions)
+    }
+
+    /// Runs the export `export` on the context. A panic in it is caught and logged instead of
+    /// aborting the host, marks the context as degraded and triggers safe mode.
+    fn guarded<T>(&mut self, export: &str, f: impl FnOnce(&mut Self) -> T) -> Option<T> {
+        let result = guard::catch_panic(export, || f(self));
+        if result.is_none() {
+            self.degraded = true;
+            self.safe_mode.trigger(Trigger::Panic);
+        }
+        result
+    }
+}
+
+/// How the agent decides where to aim and when to fire.
+#[derive(Default, Clone, Copy, PartialEq, Eq)]
+enum AimMode {
+    /// aim at the current position of the target
+    Direct,
+    /// aim so that the shot has the best chance to hit under the predicted actions of the target
+    #[default]
+    DodgeAware,
+}
+
+/// Which decision layer picks the actions of our ships.
+#[derive(Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DecisionMode {
+    /// behavior tree of prioritized behaviors, picked by the mode of each ship
+    #[default]
+    BehaviorTree,
+    /// every possible action is rated by simulating short futures of the match, enabled with
+    /// the `mcts` feature
+    Planner,
+    /// backup policy that only evades and fires, used in safe mode while the main strategy
+    /// misbehaved
+    Safe,
 }
 
+/// Action returned when deciding panicked, keeps the ship moving because nothing else about the
+/// tick can be trusted.
+const PANIC_ACTION: u32 = bindings::ActionFlags_ACTION_THRUST;
+
 #[unsafe(no_mangle)]
 pub extern "C" fn init_agent(_n_agents: u32, _agent_multiplicity: u32, seed: u32) -> Box<Context> {
+    guard::install_panic_hook();
+    guard::catch_panic("init_agent", || Box::new(new_context(seed))).unwrap_or_else(|| {
+        let mut context = Box::new(Context {
+            degraded: true,
+            ..Context::default()
+        });
+        context.safe_mode.trigger(Trigger::Panic);
+        context
+    })
+}
+
+fn new_context(seed: u32) -> Context {
     //null::<Context>() as *mut Context
-    let context = Context {
+    Context {
         config: Config::default(),
-        _seed: seed,
         world_state: WorldState::default(),
         own_ships_to_action: Vec::new(),
-        own_agent_ids: HashSet::new(),
-    };
-
-    Box::new(context)
+        own_agent_ids: IdSet::default(),
+        physics: PhysicsEstimator::default(),
+        opponents: OpponentModels::default(),
+        predictor: ActionPredictor::default(),
+        aim_mode: AimMode::default(),
+        decision_mode: if cfg!(feature = "mcts") {
+            DecisionMode::Planner
+        } else {
+            DecisionMode::BehaviorTree
+        },
+        behavior_trees: IdMap::default(),
+        ship_modes: IdMap::default(),
+        planner: Planner::new(seed),
+        compute_budget: budget::DEFAULT_BUDGET,
+        analysis: TickAnalysis::default(),
+        scratch: Scratch::default(),
+        degraded: false,
+        safe_mode: SafeMode::default(),
+    }
 }
 
 #[unsafe(no_mangle)]
 pub extern "C" fn free_context(ctx: &mut Context) {
-    *ctx = Context::default();
+    ctx.guarded("free_context", |ctx| *ctx = Context::default());
 }
 
 #[unsafe(no_mangle)]
@@ -46,33 +217,41 @@ pub extern "C" fn set_config_parameter(
     param: bindings::ConfigParameter,
     value: f32,
 ) {
-    ctx.config.update(param, value)
+    ctx.guarded("set_config_parameter", |ctx| {
+        ctx.config.update(param, value)
+    });
 }
 
 #[derive(Default)]
 struct WorldState {
+    /// tick this world state belongs to, known once the first action for it was requested
+    tick: Option<u32>,
     /// stores all ships on the playfield
     ships: Vec<Ship>,
     shots: Vec<Shot>,
     agents: Vec<Agent>,
 }
 
-#[derive(Default, Clone)]
+#[derive(Default, Clone, Copy)]
 struct Ship {
-    _hp: i32,
+    agent_id: u32,
+    hp: i32,
     pos_x: f32,
     pos_y: f32,
     heading: f32,
+    /// displacement since the last tick
+    vel_x: f32,
+    vel_y: f32,
     friendly: bool,
 }
 
-#[derive(Default, Clone)]
+#[derive(Default, Clone, Copy)]
 struct Shot {
     agent_id: u32,
-    _lifetime: i32,
+    lifetime: i32,
     pos_x: f32,
     pos_y: f32,
-    _heading: f32,
+    heading: f32,
 }
 
 #[derive(Default)]
@@ -81,9 +260,39 @@ struct Agent {
     _score: i32,
 }
 
+impl WorldState {
+    /// Empties the world state but keeps the allocated buffers.
+    fn clear(&mut self) {
+        self.tick = None;
+        self.ships.clear();
+        self.shots.clear();
+        self.agents.clear();
+    }
+}
+
 #[unsafe(no_mangle)]
 pub extern "C" fn clear_world_state(ctx: &mut Context) {
-    ctx.world_state = WorldState::default();
+    ctx.guarded("clear_world_state", |ctx| {
+        ctx.physics.observe(&ctx.world_state);
+        ctx.physics.log_calibration(&ctx.config);
+        ctx.opponents.observe(
+            &ctx.world_state,
+            &ctx.own_agent_ids,
+            ctx.physics.ship_max_velocity(&ctx.config),
+            ctx.config.ship_hit_radius,
+        );
+        ctx.predictor.observe(
+            &ctx.world_state,
+            &ctx.own_agent_ids,
+            &Kinematics::new(&ctx.config, &ctx.physics),
+            ctx.config.ship_hit_radius,
+        );
+    });
+    // cleared even when observing panicked, so that the next tick starts empty, the buffers are
+    // kept for the next tick
+    ctx.world_state.clear();
+    ctx.own_ships_to_action.clear();
+    ctx.analysis.invalidate();
 }
 
 #[unsafe(no_mangle)]
@@ -95,22 +304,36 @@ pub extern "C" fn update_ship(
     pos_y: f32,
     heading: f32,
 ) {
-    // ignore ships that have 0 or less hp
-    if hp <= 0 {
-        return;
-    }
-    let mut ship = Ship {
-        _hp: hp,
-        pos_x,
-        pos_y,
-        heading: (90.0 - heading).to_radians(),
-        friendly: false,
-    };
-    if ctx.own_agent_ids.contains(&agent_id) {
-        ship.friendly = true;
-        ctx.own_ships_to_action.push(ship.clone());
-    }
-    ctx.world_state.ships.push(ship);
+    ctx.guarded("update_ship", |ctx| {
+        // ignore ships that have 0 or less hp
+        if hp <= 0 {
+            return;
+        }
+        // ignore ships that can't be placed on the playfield
+        let (Some((pos_x, pos_y)), Some(heading)) = (
+            numeric::sanitize_position(pos_x, pos_y),
+            numeric::sanitize_heading(heading),
+        ) else {
+            log!("Agent {agent_id}: ignoring ship at {pos_x}/{pos_y}, heading {heading}");
+            return;
+        };
+        // the velocity is known once the tick is known, see `make_action`
+        let mut ship = Ship {
+            agent_id,
+            hp,
+            pos_x,
+            pos_y,
+            heading: (90.0 - heading).to_radians(),
+            vel_x: 0.0,
+            vel_y: 0.0,
+            friendly: false,
+        };
+        if ctx.own_agent_ids.contains(agent_id) {
+            ship.friendly = true;
+            ctx.own_ships_to_action.push(ctx.world_state.ships.len());
+        }
+        ctx.world_state.ships.push(ship);
+    });
 }
 
 #[unsafe(no_mangle)]
@@ -122,299 +345,246 @@ pub extern "C" fn update_shot(
     pos_y: f32,
     heading: f32,
 ) {
-    // ignore shots that have a lifetime of 0
-    if lifetime <= 0 {
-        return;
-    }
-    let shot = Shot {
-        _lifetime: lifetime,
-        pos_x,
-        pos_y,
-        _heading: (90.0 - heading).to_radians(),
-        agent_id,
-    };
-    ctx.world_state.shots.push(shot)
+    ctx.guarded("update_shot", |ctx| {
+        // ignore shots that have a lifetime of 0
+        if lifetime <= 0 {
+            return;
+        }
+        // ignore shots that can't be placed on the playfield
+        let (Some((pos_x, pos_y)), Some(heading)) = (
+            numeric::sanitize_position(pos_x, pos_y),
+            numeric::sanitize_heading(heading),
+        ) else {
+            log!("Agent {agent_id}: ignoring shot at {pos_x}/{pos_y}, heading {heading}");
+            return;
+        };
+        let shot = Shot {
+            lifetime,
+            pos_x,
+            pos_y,
+            heading: (90.0 - heading).to_radians(),
+            agent_id,
+        };
+        ctx.world_state.shots.push(shot)
+    });
 }
 
 #[unsafe(no_mangle)]
 pub extern "C" fn update_score(ctx: &mut Context, agent_id: u32, score: i32) {
-    ctx.world_state.agents.push(Agent {
-        _agent_id: agent_id,
-        _score: score,
-    })
+    ctx.guarded("update_score", |ctx| {
+        ctx.world_state.agents.push(Agent {
+            _agent_id: agent_id,
+            _score: score,
+        })
+    });
 }
 
-#[derive(Default)]
+#[derive(Default, Clone, Copy)]
 struct Action {
     enable_thrusters: bool,
     turn_direction: Option<TurnDirection>,
     fire: bool,
 }
 
-impl Into<u32> for Action {
-    fn into(self) -> u32 {
-        let enable_thrusters = if self.enable_thrusters {
-            bindings::ActionFlags_ACTION_THRUST
-        } else {
-            0
-        };
-        let turn_direction = self.turn_direction.map_or(0, |f| f.into());
-        let fire = if self.fire {
-            bindings::ActionFlags_ACTION_FIRE
-        } else {
-            0
-        };
-        enable_thrusters | turn_direction | fire
+impl From<Action> for u32 {
+    /// Resolves the flags with [`ActionBuilder`], so that every bitmask follows the same rules.
+    fn from(action: Action) -> u32 {
+        ActionBuilder::new().request(action).build().bits()
     }
 }
 
+#[derive(Clone, Copy, PartialEq, Eq)]
 enum TurnDirection {
     Left,
     Right,
 }
 
 impl Display for TurnDirection {
-    fn fmt(&self, formatter: &mut Formatter) -> Result<(), std::fmt::Error> {
+    fn fmt(&self, formatter: &mut Formatter) -> Result<(), core::fmt::Error> {
         let str = match self {
-            Self::Left => "left".to_string(),
-            Self::Right => "right".to_string(),
+            Self::Left => "left",
+            Self::Right => "right",
         };
-        write!(formatter, "{str}").unwrap();
-        Ok(())
+        formatter.write_str(str)
     }
 }
 
-impl Into<u32> for TurnDirection {
-    fn into(self) -> u32 {
-        match self {
-            Self::Left => bindings::ActionFlags_ACTION_TURN_LEFT,
-            Self::Right => bindings::ActionFlags_ACTION_TURN_RIGHT,
+impl From<TurnDirection> for u32 {
+    fn from(direction: TurnDirection) -> u32 {
+        match direction {
+            TurnDirection::Left => bindings::ActionFlags_ACTION_TURN_LEFT,
+            TurnDirection::Right => bindings::ActionFlags_ACTION_TURN_RIGHT,
         }
     }
 }
 
 #[unsafe(no_mangle)]
 pub extern "C" fn make_action(ctx: &mut Context, own_agent_id: u32, tick: u32) -> u32 {
-    // add this agent id to own agents, is used on first make_action calls to let ctx know
-    // what agents are controlled by this team
-    ctx.own_agent_ids.insert(own_agent_id);
+    ctx.guarded("make_action", |ctx| {
+        // add this agent id to own agents, is used on first make_action calls to let ctx know
+        // what agents are controlled by this team
+        ctx.own_agent_ids.insert(own_agent_id);
+        // the first action of a tick tells us which tick the world state belongs to
+        if ctx.world_state.tick != Some(tick) {
+            ctx.world_state.tick = Some(tick);
+            ctx.physics
+                .assign_velocities(&mut ctx.world_state.ships, tick);
+        }
+
+        let action = decide_action(ctx, own_agent_id, tick);
+        ctx.physics.record_action(own_agent_id, action);
+        action
+    })
+    .unwrap_or(PANIC_ACTION)
+}
 
-    let world_state = &ctx.world_state;
-    let current_ship_to_action = match ctx.own_ships_to_action.pop() {
+// every export as a function pointer of its prototype in `scubywasm_agent.h`, so that the build
+// fails when one of them does not match the header
+include!(concat!(env!("OUT_DIR"), "/exports.rs"));
+
+fn decide_action(ctx: &mut Context, own_agent_id: u32, tick: u32) -> u32 {
+    // only the ship of this agent, a dead ship is not reported and must not take the ship of a
+    // teammate
+    let ships = &ctx.world_state.ships;
+    let own_ship = ctx
+        .own_ships_to_action
+        .iter()
+        .position(|&index| {
+            ships
+                .get(index)
+                .is_some_and(|ship| ship.agent_id == own_agent_id)
+        })
+        .map(|position| ctx.own_ships_to_action.swap_remove(position))
+        .and_then(|index| ships.get(index).copied());
+    let own_ship = match own_ship {
         Some(ship) => ship,
         None => {
-            // no ship found for which an action could be calculated, so we do nothing
-            // should only be run on first action because own ships are not yet initialized
-            log!("Agent {own_agent_id}: did not find own ships");
+            // the ship of this agent is dead, or not yet known as ours on the first action
+            log!("Agent {own_agent_id}: did not find its ship");
             return bindings::ActionFlags_ACTION_NONE;
         }
     };
 
-    // shot evasion logic
-
-    // stores shots with the distance it is away from the ship
-    let mut shots: Vec<(f32, Shot)> = Vec::new();
-    // check if we have a shot free
-    let mut shot_available = true;
-    for shot in &world_state.shots {
-        // calculate distance between shot and ship
-        let x1 = shot.pos_x;
-        let y1 = shot.pos_y;
-        let x2 = current_ship_to_action.pos_x;
-        let y2 = current_ship_to_action.pos_y;
-        let distance = ((x2 - x1).powi(2) + (y2 - y1).powi(2)).sqrt().abs();
-        shots.push((distance, shot.clone()));
-        if shot.agent_id == own_agent_id {
-            shot_available = false;
-        }
-    }
-    shots.sort_by(|a, b| match a.0.partial_cmp(&b.0) {
-        Some(order) => order,
-        None => Ordering::Equal,
-    });
-    // shot with lowest distance to ship is popped first
-    shots.reverse();
-
-    // iterate through shots and calculate if they would hit
-    // first shot that is determined to hit the ship will be tried to be evaded
-    let mut evade_action = None;
-    for (distance, shot) in shots {
-        // calculate if shot is in hit radius
-
-        let x1 = current_ship_to_action.pos_x;
-        let y1 = current_ship_to_action.pos_y;
-        let x2 = shot.pos_x;
-        let y2 = shot.pos_y;
-
-        // target = my ship
-        let target_angle = (y1 - y2).atan2(x1 - x2);
-        // current angle between the shot and the ship
-        let current_angle = current_ship_to_action.heading;
-
-        // Smallest signed angle difference (-pi .. pi)
-        let mut angle_diff = target_angle - current_angle;
-
-        // Normalize to [-pi, pi]
-        while angle_diff > std::f32::consts::PI {
-            angle_diff -= 2.0 * std::f32::consts::PI;
-        }
-        while angle_diff < -std::f32::consts::PI {
-            angle_diff += 2.0 * std::f32::consts::PI;
-        }
-
-        if angle_diff.to_degrees() <= -179.0 || angle_diff.to_degrees() >= 179.0 {
-            // shot is probably not a danger for the ship
-            continue;
-        }
-
-        log!(
-            "[Tick {}] Agent: {own_agent_id}: detected shot {}/{}, angle_diff {}",
-            tick,
-            shot.pos_x,
-            shot.pos_y,
-            angle_diff.to_degrees()
-        );
-
-        // calculate if shot is in hit radius
-        let lateral_distance_target = distance * angle_diff.tan().abs();
-        let hit_radius = ctx.config.ship_hit_radius + (ctx.config.ship_hit_radius + 0.0625); // slightly increase hit radius to make evasion easier and more conservative
-
-        // shot would hit ship
-        if lateral_distance_target <= hit_radius {
-            let direction = if angle_diff > 0.0 {
-                TurnDirection::Left
-            } else {
-                TurnDirection::Right
-            };
-
+    // the analysis and the buffers are taken out of the context while they are used, because
+    // the blackboard borrows the context as well
+    let mut analysis = core::mem::take(&mut ctx.analysis);
+    analysis.update(
+        &ctx.world_state,
+        &ctx.predictor,
+        ctx.physics.shot_velocity(&ctx.config),
+    );
+    let scratch = core::mem::take(&mut ctx.scratch);
+    let budget = Budget::new(ctx.compute_budget);
+    let decision_mode = if ctx.safe_mode.update(tick) {
+        DecisionMode::Safe
+    } else {
+        ctx.decision_mode
+    };
+    // whether the main strategy decided before the budget ran out, otherwise the fallback action
+    // is used
+    let (action, decided, budget, scratch) = match decision_mode {
+        DecisionMode::BehaviorTree => {
+            // the tree and the mode are taken out of the context while they run, because the
+            // blackboard borrows it
+            let mut tree = ctx.behavior_trees.remove(own_agent_id).unwrap_or_else(|| {
+                let tree = behaviors::default_tree();
+                log!("Agent {own_agent_id}: behavior tree {tree}");
+                tree
+            });
+            let mut machine = ctx.ship_modes.remove(own_agent_id).unwrap_or_default();
+            let mut blackboard = Blackboard::sense(
+                ctx,
+                &analysis,
+                &own_ship,
+                own_agent_id,
+                tick,
+                budget,
+                scratch,
+            );
+            machine.update(&mut blackboard);
+            tree.tick(&mut blackboard);
             log!(
-                "[Tick {}] Agent: {own_agent_id}: evading shot {}/{}, angle_diff {}, turning direction: {}",
+                "[Tick {}] Agent: {own_agent_id}, behaviors: {}",
                 tick,
-                shot.pos_x,
-                shot.pos_y,
-                angle_diff.to_degrees(),
-                direction
+                Listed(&blackboard.behaviors)
             );
-            let mut action = Action::default();
-            action.turn_direction = Some(direction);
-            action.enable_thrusters = true;
-
-            evade_action = Some(action);
-        }
-    }
-
-    // acquire target
-    let mut target: Option<(f32, Ship)> = None;
-    for ship in &world_state.ships {
-        if ship.friendly {
-            // we don't want to lock an allied ship as target
-            continue;
+            // the budget ran out before any behavior set an action
+            let decided = !blackboard.behaviors.is_empty();
+            let action = if decided {
+                blackboard.action
+            } else {
+                behaviors::fallback_action(&blackboard)
+            };
+            let budget = blackboard.budget;
+            let scratch = blackboard.into_scratch();
+            ctx.behavior_trees.insert(own_agent_id, tree);
+            ctx.ship_modes.insert(own_agent_id, machine);
+            (action, decided, budget, scratch)
         }
-        // ship is enemy, so we can lock on to it
-        let x1 = ship.pos_x;
-        let y1 = ship.pos_y;
-        let x2 = current_ship_to_action.pos_x;
-        let y2 = current_ship_to_action.pos_y;
-        let distance = ((x2 - x1).powi(2) + (y2 - y1).powi(2)).sqrt();
-        if let Some((current_min_distance, _)) = &target {
-            // only update target when distance is closer
-            if current_min_distance > &distance {
-                // closer target found, update target
-                target = Some((distance, ship.clone()));
-            }
-        } else {
-            // no target yet, set target
-            target = Some((distance, ship.clone()));
+        DecisionMode::Planner => {
+            // the planner is taken out of the context while it runs, because the blackboard borrows it
+            let mut planner = core::mem::take(&mut ctx.planner);
+            let mut blackboard = Blackboard::sense(
+                ctx,
+                &analysis,
+                &own_ship,
+                own_agent_id,
+                tick,
+                budget,
+                scratch,
+            );
+            let plan = planner.plan(&mut blackboard);
+            let decided = plan.is_some();
+            let action = match plan {
+                Some(plan) => {
+                    log!(
+                        "[Tick {}] Agent: {own_agent_id}, planned {} with expected value {} from {} rollouts",
+                        tick,
+                        u32::from(plan.action),
+                        plan.expected_value,
+                        plan.rollouts
+                    );
+                    plan.action
+                }
+                None => behaviors::fallback_action(&blackboard),
+            };
+            let budget = blackboard.budget;
+            let scratch = blackboard.into_scratch();
+            ctx.planner = planner;
+            (action, decided, budget, scratch)
         }
-    }
-    let (distance, target) = match target {
-        Some(target) => target,
-        None => {
-            // no target found, so game *should* be won already
-            log!("[Tick {}] Agent: {own_agent_id} - no target found", tick);
-            if let Some(action) = evade_action {
-                return action.into();
-            } else {
-                return Action::default().into();
-            }
+        DecisionMode::Safe => {
+            let blackboard = Blackboard::sense(
+                ctx,
+                &analysis,
+                &own_ship,
+                own_agent_id,
+                tick,
+                budget,
+                scratch,
+            );
+            let action = safe_mode::safe_action(&blackboard);
+            (action, true, blackboard.budget, blackboard.into_scratch())
         }
     };
-
-    // target found, determine action
-
-    // find direction in which the target is
-    let x1 = target.pos_x;
-    let y1 = target.pos_y;
-    let x2 = current_ship_to_action.pos_x;
-    let y2 = current_ship_to_action.pos_y;
-
-    let target_angle = (y1 - y2).atan2(x1 - x2);
-    let current_angle = current_ship_to_action.heading;
-
-    // Smallest signed angle difference (-pi .. pi)
-    let mut angle_diff = target_angle - current_angle;
-
-    // Normalize to [-pi, pi]
-    while angle_diff > std::f32::consts::PI {
-        angle_diff -= 2.0 * std::f32::consts::PI;
-    }
-    while angle_diff < -std::f32::consts::PI {
-        angle_diff += 2.0 * std::f32::consts::PI;
-    }
-
-    let movement = if angle_diff.abs() < 0.01 {
-        None
-    } else if angle_diff > 0.0 {
-        Some(TurnDirection::Left)
-    } else {
-        Some(TurnDirection::Right)
-    };
-
-    // check if we are locked on target, if yes, fire shot
-
-    // calculate if shot is in hit radius
-    let lateral_distance_target = distance * angle_diff.tan().abs();
-    let hit_radius = ctx.config.ship_hit_radius;
-
-    log!(
-        "[Tick {}] Agent: {own_agent_id}, Current position: [{},{}], Target direction: {}, Current direction: {}, Target in scope: {}",
-        tick,
-        current_ship_to_action.pos_x,
-        current_ship_to_action.pos_y,
-        90.0 - target_angle.to_degrees(),
-        90.0 - current_angle.to_degrees(),
-        lateral_distance_target <= hit_radius
-    );
-
-    let mut action = Action::default();
-    // fire if shot would hit if target does not move and we are in specific range
-    if lateral_distance_target <= hit_radius && distance <= 0.3 && shot_available {
-        action.fire = true;
-        // don't turn to not distort the shot
-        action.turn_direction = None;
-    } else if shot_available {
-        action.turn_direction = movement;
-    } else {
-        // no shot available, fly straight
+    let resolved = ActionBuilder::new()
+        .request(action)
+        .shot_available(!analysis.has_shot(own_agent_id))
+        .build();
+    ctx.analysis = analysis;
+    ctx.scratch = scratch;
+    if budget.exhausted() {
         log!(
-            "[Tick {}] Agent: {own_agent_id}, No shot available, flying straight",
-            tick,
+            "[Tick {}] Agent: {own_agent_id}, budget exhausted after {budget}",
+            tick
         );
-        action.turn_direction = None;
     }
-
-    action.enable_thrusters = true;
-
-    // check if evade is set, if yes override turn direction to steer away from danger (but only if we don't shoot, to not make the shot miss)
-    if let Some(evade_action) = evade_action {
-        if !action.fire {
-            log!(
-                "[Tick {}] Agent: {own_agent_id}, evading",
-                tick,
-            );
-            action.turn_direction = evade_action.turn_direction;
-        }
+    for dropped in resolved.dropped() {
+        log!("[Tick {}] Agent: {own_agent_id}, dropped {dropped}", tick);
+    }
+    if decision_mode != DecisionMode::Safe {
+        ctx.safe_mode.record(decided, resolved.valid());
     }
 
-    action.into()
+    resolved.bits()
 }
@@ -0,0 +1,40 @@
+#[cfg(target_arch = "wasm32")]
+use crate::math::Float;
+use crate::{Ship, kinematics::KinematicState, spatial};
+
+/// Agent id of a friendly ship that a shot fired by `shooter` along its heading would pass
+/// closer than `corridor` before it expires. Friendly ships are assumed to keep their
+/// velocity.
+pub fn blocking_ally<'a>(
+    shooter: &KinematicState,
+    shooter_agent_id: u32,
+    ships: impl IntoIterator<Item = &'a Ship>,
+    shot_velocity: f32,
+    shot_lifetime: u32,
+    corridor: f32,
+) -> Option<u32> {
+    let shot_vel_x = shot_velocity * shooter.heading.cos();
+    let shot_vel_y = shot_velocity * shooter.heading.sin();
+    let duration = shot_lifetime as f32;
+    ships
+        .into_iter()
+        .filter(|ship| ship.friendly && ship.agent_id != shooter_agent_id)
+        .find(|ally| {
+            // both move with constant velocity, so their offset changes linearly over time
+            let (offset_x, offset_y) =
+                spatial::wrapped_delta((shooter.pos_x, shooter.pos_y), (ally.pos_x, ally.pos_y));
+            let rel_vel_x = ally.vel_x - shot_vel_x;
+            let rel_vel_y = ally.vel_y - shot_vel_y;
+            let rel_speed_squared = rel_vel_x.powi(2) + rel_vel_y.powi(2);
+            let closest_time = if rel_speed_squared > 0.0 {
+                (-(offset_x * rel_vel_x + offset_y * rel_vel_y) / rel_speed_squared)
+                    .clamp(0.0, duration)
+            } else {
+                0.0
+            };
+            let closest_x = offset_x + rel_vel_x * closest_time;
+            let closest_y = offset_y + rel_vel_y * closest_time;
+            (closest_x.powi(2) + closest_y.powi(2)).sqrt() <= corridor
+        })
+        .map(|ally| ally.agent_id)
+}
@@ -1,20 +1,134 @@
+use core::fmt::{Arguments, Display, Formatter, Write};
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::{
+    RwLock,
+    atomic::{AtomicBool, Ordering},
+};
+
+/// Longest message that is sent to the host, longer messages are cut off.
+const MAX_MESSAGE_LENGTH: usize = 1024;
+
+#[cfg(target_arch = "wasm32")]
 #[link(wasm_import_module = "debug")]
 unsafe extern "C" {
     fn debug_log(ptr: u32, len: u32);
 }
 
+#[cfg(target_arch = "wasm32")]
 pub fn log_str(message: &str) {
     let ptr = message.as_ptr() as u32;
     let len = message.len() as u32;
     unsafe {
         debug_log(ptr, len);
     };
-    print!("a")
 }
 
+/// Receives the log messages of native builds, set with [`set_native_log`].
+#[cfg(not(target_arch = "wasm32"))]
+static NATIVE_LOG: RwLock<Option<fn(&str)>> = RwLock::new(None);
+
+/// Whether [`NATIVE_LOG`] is set, so that disabled logging costs a single load.
+#[cfg(not(target_arch = "wasm32"))]
+static NATIVE_LOG_SET: AtomicBool = AtomicBool::new(false);
+
+/// Sends the log messages of native builds to `log`, for example [`log_to_stderr`], or turns
+/// logging off with `None`. Logging is off until this is called, the wasm host always receives
+/// the messages.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn set_native_log(log: Option<fn(&str)>) {
+    let mut native_log = NATIVE_LOG
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    *native_log = log;
+    NATIVE_LOG_SET.store(log.is_some(), Ordering::Relaxed);
+}
+
+/// Prints the message to stderr.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn log_to_stderr(message: &str) {
+    eprintln!("{message}");
+}
+
+/// Whether log messages are formatted at all.
+#[cfg(target_arch = "wasm32")]
+pub fn enabled() -> bool {
+    true
+}
+
+/// Whether log messages are formatted at all.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn enabled() -> bool {
+    NATIVE_LOG_SET.load(Ordering::Relaxed)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn log_str(message: &str) {
+    let native_log = NATIVE_LOG
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    if let Some(log) = *native_log {
+        log(message);
+    }
+}
+
+/// Formats the message into a buffer on the stack, so that logging does not allocate.
+pub fn log_args(args: Arguments) {
+    let mut buffer = MessageBuffer {
+        bytes: [0; MAX_MESSAGE_LENGTH],
+        len: 0,
+    };
+    // the buffer never fails, it cuts the message off instead
+    let _ = buffer.write_fmt(args);
+    log_str(buffer.as_str());
+}
+
+/// Displays the names separated by commas, without joining them into a new string.
+pub struct Listed<'a>(pub &'a [&'static str]);
+
+impl Display for Listed<'_> {
+    fn fmt(&self, formatter: &mut Formatter) -> core::fmt::Result {
+        for (index, name) in self.0.iter().enumerate() {
+            if index > 0 {
+                formatter.write_str(", ")?;
+            }
+            formatter.write_str(name)?;
+        }
+        Ok(())
+    }
+}
+
+struct MessageBuffer {
+    bytes: [u8; MAX_MESSAGE_LENGTH],
+    len: usize,
+}
+
+impl MessageBuffer {
+    fn as_str(&self) -> &str {
+        // only whole characters are written, see `write_str`
+        core::str::from_utf8(&self.bytes[..self.len]).unwrap_or_default()
+    }
+}
+
+impl Write for MessageBuffer {
+    fn write_str(&mut self, text: &str) -> core::fmt::Result {
+        let mut end = text.len().min(MAX_MESSAGE_LENGTH - self.len);
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        self.bytes[self.len..self.len + end].copy_from_slice(&text.as_bytes()[..end]);
+        self.len += end;
+        Ok(())
+    }
+}
+
+/// Logs a message to the host, or natively to the function set with
+/// [`set_native_log`](crate::set_native_log). The arguments are only evaluated and formatted when
+/// logging is enabled.
 #[macro_export]
 macro_rules! log {
     ($($arg:tt)*) => {{
-        $crate::logging::log_str(format!($($arg)*).as_ref());
+        if $crate::logging::enabled() {
+            $crate::logging::log_args(format_args!($($arg)*));
+        }
     }};
 }
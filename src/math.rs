@@ -0,0 +1,65 @@
+//! Float functions that std provides but core does not, for the build without std on wasm32.
+//! They use the pure Rust port of musl's libm, like std itself does on wasm32.
+
+/// The methods of `f32` that are missing in core, with the same names so that the code does not
+/// depend on whether std is available.
+pub trait Float {
+    fn sqrt(self) -> Self;
+    fn sin(self) -> Self;
+    fn cos(self) -> Self;
+    fn atan2(self, other: Self) -> Self;
+    fn ln(self) -> Self;
+    fn ceil(self) -> Self;
+    fn round(self) -> Self;
+    fn powi(self, exponent: i32) -> Self;
+    fn rem_euclid(self, rhs: Self) -> Self;
+}
+
+impl Float for f32 {
+    fn sqrt(self) -> f32 {
+        libm::sqrtf(self)
+    }
+
+    fn sin(self) -> f32 {
+        libm::sinf(self)
+    }
+
+    fn cos(self) -> f32 {
+        libm::cosf(self)
+    }
+
+    fn atan2(self, other: f32) -> f32 {
+        libm::atan2f(self, other)
+    }
+
+    fn ln(self) -> f32 {
+        libm::logf(self)
+    }
+
+    fn ceil(self) -> f32 {
+        libm::ceilf(self)
+    }
+
+    fn round(self) -> f32 {
+        libm::roundf(self)
+    }
+
+    fn powi(self, exponent: i32) -> f32 {
+        // only small exponents are used, multiplying keeps the result exact for squares
+        let mut result = 1.0;
+        for _ in 0..exponent.unsigned_abs() {
+            result *= self;
+        }
+        if exponent < 0 { 1.0 / result } else { result }
+    }
+
+    fn rem_euclid(self, rhs: f32) -> f32 {
+        // same as std
+        let remainder = self % rhs;
+        if remainder < 0.0 {
+            remainder + rhs.abs()
+        } else {
+            remainder
+        }
+    }
+}
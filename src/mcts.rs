@@ -0,0 +1,615 @@
+#[cfg(target_arch = "wasm32")]
+use crate::math::Float;
+use crate::prelude::*;
+use crate::{
+    Action,
+    behaviors::Blackboard,
+    bindings,
+    kinematics::{KinematicState, Kinematics},
+    prediction::{ActionDistribution, MOVEMENT_ACTIONS},
+    spatial, utility,
+};
+
+/// Number of ticks every simulated future lasts, unless the budget only allows shorter ones.
+const ROLLOUT_DEPTH: u32 = 24;
+
+/// Number of ships closest to ours that are simulated, including ours. Ships further away
+/// rarely matter within [`ROLLOUT_DEPTH`] ticks and would make every tick of a crowded world
+/// expensive.
+const MAX_SIMULATED_SHIPS: usize = 12;
+
+/// Number of shots closest to our ship that are simulated.
+const MAX_SIMULATED_SHOTS: usize = 12;
+
+/// Distance checks between two simulated objects that cost about as much as one unit of the
+/// budget, i.e. as simulating one ship for one tick.
+const CHECKS_PER_UNIT: u32 = 8;
+
+/// Weight of rewards one tick later than the one before, earlier hits are worth more.
+const DISCOUNT: f32 = 0.95;
+
+/// Exploration constant of UCB1.
+const EXPLORATION: f32 = 1.4;
+
+/// Chance that our ship picks a random action instead of the default policy during a rollout.
+const RANDOM_ACTION_CHANCE: f32 = 0.2;
+
+/// Small and fast pseudo random number generator, the planner only needs it to be cheap and
+/// reproducible from the seed given by the host.
+pub struct XorShift {
+    state: u32,
+}
+
+impl XorShift {
+    pub fn new(seed: u32) -> Self {
+        // the state must never be zero
+        Self { state: seed | 1 }
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let mut x = self.state;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.state = x;
+        x
+    }
+
+    /// Uniform float in [0, 1).
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u32() >> 8) as f32 / (1u32 << 24) as f32
+    }
+}
+
+/// Visits and accumulated value of one candidate action at the root.
+#[derive(Default, Clone, Copy)]
+struct Arm {
+    action: Action,
+    visits: u32,
+    value: f32,
+}
+
+impl Arm {
+    fn mean(&self) -> f32 {
+        if self.visits == 0 {
+            0.0
+        } else {
+            self.value / self.visits as f32
+        }
+    }
+}
+
+/// Outcome of a planning call.
+pub struct Plan {
+    pub action: Action,
+    /// expected hits dealt minus hits received of the chosen action
+    pub expected_value: f32,
+    /// number of simulated futures
+    pub rollouts: u32,
+}
+
+/// Picks actions by simulating short futures of the match for every candidate action and
+/// choosing the one with the best expected outcome. The candidate that is simulated next is
+/// picked with UCB1, so promising actions get more simulations. Futures are simulated until the
+/// budget is spent, they are shortened when the budget would not simulate every candidate
+/// otherwise.
+pub struct Planner {
+    rng: XorShift,
+    /// the world at the start of the decision
+    root: Simulation,
+    /// the world during a rollout, reset to `root` before each one
+    rollout: Simulation,
+}
+
+impl Default for Planner {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl Planner {
+    pub fn new(seed: u32) -> Self {
+        Self {
+            rng: XorShift::new(seed),
+            root: Simulation::default(),
+            rollout: Simulation::default(),
+        }
+    }
+
+    /// Best action found before the budget ran out, `None` when not even every candidate
+    /// could be simulated once.
+    pub fn plan(&mut self, blackboard: &mut Blackboard) -> Option<Plan> {
+        let mut arms = [Arm::default(); utility::CANDIDATE_ACTIONS];
+        for (arm, action) in arms.iter_mut().zip(utility::candidate_actions()) {
+            arm.action = action;
+        }
+        self.root.reset(blackboard);
+        let tick_cost = self.root.tick_cost();
+        let depth =
+            (blackboard.budget.remaining() / (arms.len() as u32 * tick_cost)).min(ROLLOUT_DEPTH);
+        if depth == 0 {
+            return None;
+        }
+        let rollout_cost = depth * tick_cost;
+
+        let mut rollouts = 0;
+        while blackboard.budget.try_spend(rollout_cost) {
+            let index = select(&arms, rollouts);
+            self.rollout.copy_from(&self.root);
+            let value = self
+                .rollout
+                .rollout(arms[index].action, depth, blackboard, &mut self.rng);
+            arms[index].visits += 1;
+            arms[index].value += value;
+            rollouts += 1;
+        }
+
+        if arms.iter().any(|arm| arm.visits == 0) {
+            return None;
+        }
+        let best = arms.iter().max_by(|a, b| a.mean().total_cmp(&b.mean()))?;
+        Some(Plan {
+            action: best.action,
+            expected_value: best.mean(),
+            rollouts,
+        })
+    }
+}
+
+/// Index of the arm to simulate next, every arm is tried once before UCB1 takes over.
+fn select(arms: &[Arm], iteration: u32) -> usize {
+    if let Some(index) = arms.iter().position(|arm| arm.visits == 0) {
+        return index;
+    }
+    let total = (iteration.max(1) as f32).ln();
+    arms.iter()
+        .enumerate()
+        .map(|(index, arm)| {
+            let bonus = EXPLORATION * (total / arm.visits as f32).sqrt();
+            (index, arm.mean() + bonus)
+        })
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map_or(0, |(index, _)| index)
+}
+
+#[derive(Clone)]
+struct SimShip {
+    agent_id: u32,
+    friendly: bool,
+    state: KinematicState,
+    alive: bool,
+}
+
+#[derive(Clone)]
+struct SimShot {
+    agent_id: u32,
+    pos_x: f32,
+    pos_y: f32,
+    vel_x: f32,
+    vel_y: f32,
+    lifetime: i32,
+}
+
+/// Simplified copy of the world around our ship that can be stepped forward.
+#[derive(Default)]
+struct Simulation {
+    ships: Vec<SimShip>,
+    shots: Vec<SimShot>,
+    /// result of the queries for the closest ships and shots
+    nearest: Vec<(usize, f32)>,
+}
+
+impl Simulation {
+    /// Copies the ships and shots closest to our ship from the blackboard into the simulation.
+    fn reset(&mut self, blackboard: &Blackboard) {
+        let own_position = (blackboard.own_state.pos_x, blackboard.own_state.pos_y);
+        self.ships.clear();
+        self.ships.push(SimShip {
+            agent_id: blackboard.own_agent_id,
+            friendly: true,
+            state: blackboard.own_state,
+            alive: true,
+        });
+        blackboard.analysis.ships.nearest(
+            own_position,
+            MAX_SIMULATED_SHIPS - 1,
+            |index| {
+                blackboard
+                    .ships
+                    .get(index)
+                    .is_some_and(|ship| ship.agent_id != blackboard.own_agent_id)
+            },
+            &mut self.nearest,
+        );
+        self.ships
+            .extend(self.nearest.iter().filter_map(|&(index, _)| {
+                let ship = blackboard.ships.get(index)?;
+                Some(SimShip {
+                    agent_id: ship.agent_id,
+                    friendly: ship.friendly,
+                    state: KinematicState::from(ship),
+                    alive: true,
+                })
+            }));
+        blackboard.analysis.shots.nearest(
+            own_position,
+            MAX_SIMULATED_SHOTS,
+            |_| true,
+            &mut self.nearest,
+        );
+        self.shots.clear();
+        self.shots
+            .extend(self.nearest.iter().filter_map(|&(index, _)| {
+                let shot = blackboard.shots.get(index)?;
+                Some(SimShot {
+                    agent_id: shot.agent_id,
+                    pos_x: shot.pos_x,
+                    pos_y: shot.pos_y,
+                    vel_x: blackboard.shot_velocity * shot.heading.cos(),
+                    vel_y: blackboard.shot_velocity * shot.heading.sin(),
+                    lifetime: shot.lifetime,
+                })
+            }));
+    }
+
+    /// Units one simulated tick costs at most: every ship and shot moves, every ship looks for
+    /// the nearest enemy and every shot is checked against every ship for hits. Every ship may
+    /// fire one more shot during the rollout.
+    fn tick_cost(&self) -> u32 {
+        let ships = self.ships.len() as u32;
+        let shots = (self.shots.len() + self.ships.len()) as u32;
+        (ships + shots + (ships * (ships + shots)).div_ceil(CHECKS_PER_UNIT)).max(1)
+    }
+
+    /// Copies `other` into the simulation, keeping the buffers of the simulation.
+    fn copy_from(&mut self, other: &Self) {
+        self.ships.clone_from(&other.ships);
+        self.shots.clone_from(&other.shots);
+    }
+
+    /// Plays `first_action` and then the default policies for `depth` ticks and returns the
+    /// discounted hits dealt minus hits received.
+    fn rollout(
+        &mut self,
+        first_action: Action,
+        depth: u32,
+        blackboard: &Blackboard,
+        rng: &mut XorShift,
+    ) -> f32 {
+        let own_agent_id = blackboard.own_agent_id;
+        let mut value = 0.0;
+        let mut discount = 1.0;
+        for tick in 0..depth {
+            for index in 0..self.ships.len() {
+                let ship = &self.ships[index];
+                if !ship.alive {
+                    continue;
+                }
+                let action = if ship.agent_id == own_agent_id {
+                    if tick == 0 {
+                        u32::from(first_action)
+                    } else if rng.next_f32() < RANDOM_ACTION_CHANCE {
+                        random_action(rng)
+                    } else {
+                        self.attack_policy(index, blackboard)
+                    }
+                } else if ship.friendly {
+                    self.attack_policy(index, blackboard)
+                } else {
+                    let distribution = blackboard.analysis.prediction(ship.agent_id, false);
+                    let target = self.nearest_enemy(ship);
+                    sample(&distribution, rng) | fire_policy(ship, target, blackboard)
+                };
+                self.apply(index, action, &blackboard.kinematics, blackboard);
+            }
+            value += discount * self.move_shots(blackboard);
+            discount *= DISCOUNT;
+            if !self
+                .ships
+                .iter()
+                .any(|ship| ship.agent_id == own_agent_id && ship.alive)
+            {
+                break;
+            }
+        }
+        value
+    }
+
+    /// Turns towards the nearest enemy of the ship, fires when it is in front of it.
+    fn attack_policy(&self, index: usize, blackboard: &Blackboard) -> u32 {
+        let ship = &self.ships[index];
+        let Some(target) = self.nearest_enemy(ship) else {
+            return bindings::ActionFlags_ACTION_NONE;
+        };
+        let (dx, dy) = offset(ship, target);
+        let angle = dy.atan2(dx);
+        let diff = (angle - ship.state.heading)
+            .sin()
+            .atan2((angle - ship.state.heading).cos());
+        let turn = if diff.abs() < blackboard.kinematics.turn_rate / 2.0 {
+            0
+        } else if diff > 0.0 {
+            bindings::ActionFlags_ACTION_TURN_LEFT
+        } else {
+            bindings::ActionFlags_ACTION_TURN_RIGHT
+        };
+        bindings::ActionFlags_ACTION_THRUST | turn | fire_policy(ship, Some(target), blackboard)
+    }
+
+    fn nearest_enemy(&self, ship: &SimShip) -> Option<&SimShip> {
+        let distance = |other: &SimShip| {
+            let (dx, dy) = offset(ship, other);
+            dx.powi(2) + dy.powi(2)
+        };
+        self.ships
+            .iter()
+            .filter(|other| other.alive && other.friendly != ship.friendly)
+            .min_by(|a, b| distance(a).total_cmp(&distance(b)))
+    }
+
+    fn apply(
+        &mut self,
+        index: usize,
+        action: u32,
+        kinematics: &Kinematics,
+        blackboard: &Blackboard,
+    ) {
+        let ship = &mut self.ships[index];
+        ship.state = kinematics.step(&ship.state, action);
+        let agent_id = ship.agent_id;
+        let state = ship.state;
+        // every agent can only have one shot at a time
+        if action & bindings::ActionFlags_ACTION_FIRE != 0
+            && !self.shots.iter().any(|shot| shot.agent_id == agent_id)
+        {
+            self.shots.push(SimShot {
+                agent_id,
+                pos_x: state.pos_x,
+                pos_y: state.pos_y,
+                vel_x: blackboard.shot_velocity * state.heading.cos(),
+                vel_y: blackboard.shot_velocity * state.heading.sin(),
+                lifetime: blackboard.shot_lifetime as i32,
+            });
+        }
+    }
+
+    /// Moves the shots and resolves hits, returns hits dealt minus hits received by our team.
+    fn move_shots(&mut self, blackboard: &Blackboard) -> f32 {
+        let own_agent_id = blackboard.own_agent_id;
+        let mut value = 0.0;
+        for shot in &mut self.shots {
+            shot.pos_x = (shot.pos_x + shot.vel_x).rem_euclid(spatial::PLAYFIELD_SIZE);
+            shot.pos_y = (shot.pos_y + shot.vel_y).rem_euclid(spatial::PLAYFIELD_SIZE);
+            shot.lifetime -= 1;
+            let hit = self.ships.iter_mut().find(|ship| {
+                ship.alive
+                    && ship.agent_id != shot.agent_id
+                    && spatial::wrapped_distance(
+                        (ship.state.pos_x, ship.state.pos_y),
+                        (shot.pos_x, shot.pos_y),
+                    ) <= blackboard.hit_radius
+            });
+            if let Some(ship) = hit {
+                if ship.agent_id == own_agent_id {
+                    value -= 1.0;
+                } else if shot.agent_id == own_agent_id {
+                    // hitting an ally costs as much as being hit
+                    value += if ship.friendly { -1.0 } else { 1.0 };
+                }
+                ship.alive = false;
+                shot.lifetime = 0;
+            }
+        }
+        self.shots.retain(|shot| shot.lifetime > 0);
+        value
+    }
+}
+
+/// Fires when a shot along the current heading of `ship` would pass `target` within the hit
+/// radius.
+fn fire_policy(ship: &SimShip, target: Option<&SimShip>, blackboard: &Blackboard) -> u32 {
+    let Some(target) = target else {
+        return 0;
+    };
+    let (dx, dy) = offset(ship, target);
+    let along = dx * ship.state.heading.cos() + dy * ship.state.heading.sin();
+    let lateral = (dx * ship.state.heading.sin() - dy * ship.state.heading.cos()).abs();
+    let reach = blackboard.shot_velocity * blackboard.shot_lifetime as f32;
+    if along > 0.0 && along <= reach && lateral <= blackboard.hit_radius {
+        bindings::ActionFlags_ACTION_FIRE
+    } else {
+        0
+    }
+}
+
+/// Shortest offset from `ship` to `other` on the wrapping playfield.
+fn offset(ship: &SimShip, other: &SimShip) -> (f32, f32) {
+    spatial::wrapped_delta(
+        (ship.state.pos_x, ship.state.pos_y),
+        (other.state.pos_x, other.state.pos_y),
+    )
+}
+
+fn random_action(rng: &mut XorShift) -> u32 {
+    let movement = MOVEMENT_ACTIONS[rng.next_u32() as usize % MOVEMENT_ACTIONS.len()];
+    if rng.next_u32().is_multiple_of(2) {
+        movement | bindings::ActionFlags_ACTION_FIRE
+    } else {
+        movement
+    }
+}
+
+/// Draws an action from a distribution over [`MOVEMENT_ACTIONS`].
+fn sample(distribution: &ActionDistribution, rng: &mut XorShift) -> u32 {
+    let mut remaining = rng.next_f32() * distribution.iter().sum::<f32>();
+    for (probability, action) in distribution.iter().zip(MOVEMENT_ACTIONS) {
+        remaining -= probability;
+        if remaining <= 0.0 {
+            return action;
+        }
+    }
+    MOVEMENT_ACTIONS[0]
+}
+
+// the planner is only used as decision mode with the `mcts` feature, these tests run without it
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Context, Ship, analysis::TickAnalysis, behaviors::Scratch, budget::Budget};
+
+    const OWN: u32 = 0;
+    const ALLY: u32 = 1;
+    const ENEMY: u32 = 2;
+
+    fn arms(visits_and_values: &[(u32, f32)]) -> Vec<Arm> {
+        visits_and_values
+            .iter()
+            .map(|&(visits, value)| Arm {
+                visits,
+                value,
+                ..Arm::default()
+            })
+            .collect()
+    }
+
+    /// Context with the config of a match and the `ships` as world state.
+    fn context(ships: Vec<Ship>) -> Context {
+        let mut ctx = Context::default();
+        ctx.config.ship_max_turn_rate = 10.0;
+        ctx.config.ship_max_velocity = 0.005;
+        ctx.config.ship_hit_radius = 0.02;
+        ctx.config.shot_velocity = 0.01;
+        ctx.config.shot_lifetime = 40.0;
+        ctx.world_state.tick = Some(1);
+        ctx.world_state.ships = ships;
+        ctx
+    }
+
+    fn ship(agent_id: u32, pos_x: f32, pos_y: f32, heading: f32) -> Ship {
+        Ship {
+            agent_id,
+            hp: 3,
+            pos_x,
+            pos_y,
+            heading,
+            friendly: agent_id != ENEMY,
+            ..Ship::default()
+        }
+    }
+
+    /// Plans the action of our ship, the first ship of `ctx`, with `budget` work units.
+    fn plan(ctx: &Context, budget: u32) -> Option<Plan> {
+        let mut analysis = TickAnalysis::default();
+        analysis.update(&ctx.world_state, &ctx.predictor, ctx.config.shot_velocity);
+        let mut blackboard = Blackboard::sense(
+            ctx,
+            &analysis,
+            &ctx.world_state.ships[0],
+            OWN,
+            1,
+            Budget::new(budget),
+            Scratch::default(),
+        );
+        Planner::new(7).plan(&mut blackboard)
+    }
+
+    #[test]
+    fn every_arm_is_tried_once_before_ucb1() {
+        let mut arms = arms(&[(0, 0.0); 4]);
+        for iteration in 0..4 {
+            let index = select(&arms, iteration);
+            assert_eq!(index, iteration as usize);
+            arms[index].visits += 1;
+        }
+        // an untried arm goes before a good one, then the best mean wins among equal visits
+        let mut arms = self::arms(&[(1, -1.0), (1, 1.0), (0, 0.0)]);
+        assert_eq!(select(&arms, 2), 2);
+        arms[2].visits = 1;
+        assert_eq!(select(&arms, 3), 1);
+    }
+
+    #[test]
+    fn rarely_tried_arms_are_explored() {
+        let arms = arms(&[(100, 50.0), (1, 0.0)]);
+        assert_eq!(select(&arms, 101), 1);
+    }
+
+    #[test]
+    fn shots_score_hits_dealt_received_and_on_allies() {
+        let ctx = context(vec![ship(OWN, 0.5, 0.5, 0.0)]);
+        let mut analysis = TickAnalysis::default();
+        analysis.update(&ctx.world_state, &ctx.predictor, ctx.config.shot_velocity);
+        let blackboard = Blackboard::sense(
+            &ctx,
+            &analysis,
+            &ctx.world_state.ships[0],
+            OWN,
+            1,
+            Budget::new(0),
+            Scratch::default(),
+        );
+        let sim_ship = |agent_id, pos_x| SimShip {
+            agent_id,
+            friendly: agent_id != ENEMY,
+            state: KinematicState {
+                pos_x,
+                pos_y: 0.5,
+                ..KinematicState::default()
+            },
+            alive: true,
+        };
+        // a shot that lands on the ship at `pos_x` on its next move
+        let sim_shot = |agent_id, pos_x: f32| SimShot {
+            agent_id,
+            pos_x: pos_x - 0.01,
+            pos_y: 0.5,
+            vel_x: 0.01,
+            vel_y: 0.0,
+            lifetime: 10,
+        };
+        let mut simulation = Simulation::default();
+        let mut score = |ships, shots| {
+            simulation.ships = ships;
+            simulation.shots = shots;
+            simulation.move_shots(&blackboard)
+        };
+
+        let dealt = score(vec![sim_ship(ENEMY, 0.2)], vec![sim_shot(OWN, 0.2)]);
+        assert_eq!(dealt, 1.0);
+        let received = score(vec![sim_ship(OWN, 0.2)], vec![sim_shot(ENEMY, 0.2)]);
+        assert_eq!(received, -1.0);
+        let on_ally = score(vec![sim_ship(ALLY, 0.2)], vec![sim_shot(OWN, 0.2)]);
+        assert_eq!(on_ally, -1.0);
+        // only our own shots count as hits dealt
+        let by_ally = score(vec![sim_ship(ENEMY, 0.2)], vec![sim_shot(ALLY, 0.2)]);
+        assert_eq!(by_ally, 0.0);
+        let missed = score(vec![sim_ship(ENEMY, 0.2)], vec![sim_shot(OWN, 0.6)]);
+        assert_eq!(missed, 0.0);
+        assert!(simulation.ships.iter().all(|ship| ship.alive));
+        assert_eq!(simulation.shots.len(), 1);
+    }
+
+    #[test]
+    fn no_plan_without_budget_for_one_tick_per_candidate() {
+        let ctx = context(vec![ship(OWN, 0.5, 0.5, 0.0), ship(ENEMY, 0.7, 0.5, 0.0)]);
+        assert!(plan(&ctx, 0).is_none());
+        assert!(plan(&ctx, utility::CANDIDATE_ACTIONS as u32 - 1).is_none());
+    }
+
+    #[test]
+    fn planner_fires_at_target_straight_ahead() {
+        let ctx = context(vec![
+            ship(OWN, 0.5, 0.5, 0.0),
+            // facing away, so that it can't fire back
+            ship(ENEMY, 0.65, 0.5, 0.0),
+        ]);
+        let plan = plan(&ctx, 100_000).expect("every candidate is simulated");
+        assert_ne!(
+            u32::from(plan.action) & bindings::ActionFlags_ACTION_FIRE,
+            0,
+            "expected value {}",
+            plan.expected_value
+        );
+        assert!(plan.expected_value > 0.0);
+    }
+}
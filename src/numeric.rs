@@ -0,0 +1,39 @@
+use core::f32::consts::{PI, TAU};
+
+#[cfg(target_arch = "wasm32")]
+use crate::math::Float;
+use crate::spatial::PLAYFIELD_SIZE;
+
+/// Normalizes an angle to [-pi, pi). Unlike repeated adding of full turns, this terminates for
+/// huge and infinite angles, which become NaN.
+pub fn normalize_angle(angle: f32) -> f32 {
+    (angle + PI).rem_euclid(TAU) - PI
+}
+
+/// Distance of the point at `offset` from the line through the origin in the direction `angle`.
+/// Uses the cross product, so it stays finite when the point is beside the origin, where
+/// `distance * tan(angle difference)` would blow up.
+pub fn lateral_distance(offset: (f32, f32), angle: f32) -> f32 {
+    (offset.0 * angle.sin() - offset.1 * angle.cos()).abs()
+}
+
+/// Distance of the point at `offset` along the line through the origin in the direction
+/// `angle`, negative when the point is behind the origin.
+pub fn forward_distance(offset: (f32, f32), angle: f32) -> f32 {
+    offset.0 * angle.cos() + offset.1 * angle.sin()
+}
+
+/// Position wrapped onto the playfield, `None` when it is not finite and can't be placed.
+pub fn sanitize_position(pos_x: f32, pos_y: f32) -> Option<(f32, f32)> {
+    (pos_x.is_finite() && pos_y.is_finite()).then(|| {
+        (
+            pos_x.rem_euclid(PLAYFIELD_SIZE),
+            pos_y.rem_euclid(PLAYFIELD_SIZE),
+        )
+    })
+}
+
+/// Heading in degrees reduced to [0, 360), `None` when it is not finite.
+pub fn sanitize_heading(heading: f32) -> Option<f32> {
+    heading.is_finite().then(|| heading.rem_euclid(360.0))
+}
@@ -0,0 +1,368 @@
+use core::fmt::{Display, Formatter};
+
+#[cfg(target_arch = "wasm32")]
+use crate::math::Float;
+use crate::{
+    Ship, WorldState,
+    id_map::{IdMap, IdSet},
+    kinematics, log, spatial,
+};
+
+/// Weight of a new sample in the moving averages, small enough that a few odd ticks don't
+/// flip the classification but large enough to notice a change of tactics during the match.
+const SMOOTHING: f32 = 0.05;
+
+/// Number of observed ticks before an agent is classified.
+const MIN_OBSERVATIONS: u32 = 30;
+
+/// Heading changes larger than this (in radians) while threatened count as dodging.
+const DODGE_TURN: f32 = 0.01;
+
+/// Observed behavior pattern of an enemy agent.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Archetype {
+    /// not enough observations or no clear pattern
+    #[default]
+    Unknown,
+    /// flies straight at our ships
+    Chaser,
+    /// circles around our ships at a distance
+    Orbiter,
+    /// mostly stands still
+    Camper,
+    /// turns away whenever one of our shots comes close
+    Dodger,
+}
+
+impl Display for Archetype {
+    fn fmt(&self, formatter: &mut Formatter) -> Result<(), core::fmt::Error> {
+        let str = match self {
+            Self::Unknown => "unknown",
+            Self::Chaser => "chaser",
+            Self::Orbiter => "orbiter",
+            Self::Camper => "camper",
+            Self::Dodger => "dodger",
+        };
+        write!(formatter, "{str}")
+    }
+}
+
+/// Movement and firing statistics of one enemy agent over the match.
+#[derive(Default, Clone, Copy)]
+pub struct OpponentProfile {
+    /// ticks in which the ship of the agent was observed
+    pub observations: u32,
+    /// shots fired per tick
+    pub fire_rate: f32,
+    /// speed relative to the max velocity
+    pub speed_ratio: f32,
+    /// share of the movement that goes towards the nearest own ship, negative when fleeing
+    pub closing_ratio: f32,
+    /// share of the movement that goes around the nearest own ship
+    pub orbit_ratio: f32,
+    /// share of the threatened ticks in which the agent turned
+    pub dodge_rate: f32,
+    /// number of ticks in which one of our shots threatened the agent
+    pub threatened: u32,
+    pub archetype: Archetype,
+    /// heading and shot lifetime on the last observed tick
+    last_heading: Option<f32>,
+    last_shot_lifetime: Option<i32>,
+}
+
+impl OpponentProfile {
+    fn classify(&self) -> Archetype {
+        if self.observations < MIN_OBSERVATIONS {
+            Archetype::Unknown
+        } else if self.speed_ratio < 0.25 {
+            Archetype::Camper
+        } else if self.closing_ratio > 0.5 {
+            Archetype::Chaser
+        } else if self.orbit_ratio > 0.6 {
+            Archetype::Orbiter
+        } else if self.threatened >= 5 && self.dodge_rate > 0.5 {
+            Archetype::Dodger
+        } else {
+            Archetype::Unknown
+        }
+    }
+}
+
+/// Behavior profiles of all enemy agents, kept for the whole match.
+#[derive(Default)]
+pub struct OpponentModels {
+    profiles: IdMap<OpponentProfile>,
+}
+
+impl OpponentModels {
+    /// Updates the profiles with the world state of the tick that just ended. Has to be called
+    /// before the world state is cleared.
+    pub fn observe(
+        &mut self,
+        world_state: &WorldState,
+        own_agent_ids: &IdSet,
+        max_velocity: f32,
+        hit_radius: f32,
+    ) {
+        for ship in world_state.ships.iter().filter(|ship| !ship.friendly) {
+            let profile = self
+                .profiles
+                .get_or_insert_with(ship.agent_id, OpponentProfile::default);
+            profile.observations += 1;
+
+            let speed = (ship.vel_x.powi(2) + ship.vel_y.powi(2)).sqrt();
+            if max_velocity > 0.0 {
+                ema(
+                    &mut profile.speed_ratio,
+                    (speed / max_velocity).min(1.0),
+                    profile.observations,
+                );
+            }
+            if let Some(nearest) = nearest(ship, world_state.ships.iter().filter(|s| s.friendly))
+                && speed > 0.0
+            {
+                let (dx, dy) = spatial::wrapped_delta(
+                    (ship.pos_x, ship.pos_y),
+                    (nearest.pos_x, nearest.pos_y),
+                );
+                let distance = (dx.powi(2) + dy.powi(2)).sqrt();
+                if distance > 0.0 {
+                    let radial = (ship.vel_x * dx + ship.vel_y * dy) / (speed * distance);
+                    let tangential = (ship.vel_x * dy - ship.vel_y * dx) / (speed * distance);
+                    ema(&mut profile.closing_ratio, radial, profile.observations);
+                    ema(
+                        &mut profile.orbit_ratio,
+                        tangential.abs(),
+                        profile.observations,
+                    );
+                }
+            }
+
+            let own_shots = world_state
+                .shots
+                .iter()
+                .filter(|shot| own_agent_ids.contains(shot.agent_id));
+            let threatened = kinematics::threatened_by(ship, own_shots, hit_radius);
+            if threatened && let Some(last_heading) = profile.last_heading {
+                profile.threatened += 1;
+                let turned = angle_between(last_heading, ship.heading) > DODGE_TURN;
+                ema(
+                    &mut profile.dodge_rate,
+                    if turned { 1.0 } else { 0.0 },
+                    profile.threatened,
+                );
+            }
+            profile.last_heading = Some(ship.heading);
+
+            let shot = world_state
+                .shots
+                .iter()
+                .find(|shot| shot.agent_id == ship.agent_id);
+            // a shot is new when the agent had none or its lifetime did not go down
+            let fired = match (shot, profile.last_shot_lifetime) {
+                (Some(shot), Some(last)) => shot.lifetime >= last,
+                (Some(_), None) => true,
+                (None, _) => false,
+            };
+            ema(
+                &mut profile.fire_rate,
+                if fired { 1.0 } else { 0.0 },
+                profile.observations,
+            );
+            profile.last_shot_lifetime = shot.map(|shot| shot.lifetime);
+
+            let archetype = profile.classify();
+            if archetype != profile.archetype {
+                log!(
+                    "Agent {} classified as {} (fire rate {}, speed {}, closing {}, orbit {}, dodge {})",
+                    ship.agent_id,
+                    archetype,
+                    profile.fire_rate,
+                    profile.speed_ratio,
+                    profile.closing_ratio,
+                    profile.orbit_ratio,
+                    profile.dodge_rate
+                );
+                profile.archetype = archetype;
+            }
+        }
+    }
+
+    /// Archetype of the agent, `Unknown` for agents that were never observed.
+    pub fn archetype(&self, agent_id: u32) -> Archetype {
+        self.profiles
+            .get(agent_id)
+            .map_or(Archetype::Unknown, |profile| profile.archetype)
+    }
+}
+
+/// Exponential moving average that behaves like a plain mean for the first samples, so the
+/// start value does not bias the average.
+fn ema(average: &mut f32, sample: f32, samples: u32) {
+    let weight = SMOOTHING.max(1.0 / samples.max(1) as f32);
+    *average += (sample - *average) * weight;
+}
+
+fn nearest<'a>(ship: &Ship, candidates: impl Iterator<Item = &'a Ship>) -> Option<&'a Ship> {
+    let distance = |other: &Ship| {
+        spatial::wrapped_distance((ship.pos_x, ship.pos_y), (other.pos_x, other.pos_y))
+    };
+    candidates.min_by(|a, b| distance(a).total_cmp(&distance(b)))
+}
+
+fn angle_between(a: f32, b: f32) -> f32 {
+    let diff = (b - a).rem_euclid(2.0 * core::f32::consts::PI);
+    diff.min(2.0 * core::f32::consts::PI - diff)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Shot;
+
+    const MAX_VELOCITY: f32 = 0.005;
+    const HIT_RADIUS: f32 = 0.02;
+    /// agent id of our ship, which stays in the center
+    const OWN: u32 = 0;
+    const ENEMY: u32 = 1;
+
+    fn enemy(pos_x: f32, pos_y: f32, vel_x: f32, vel_y: f32, heading: f32) -> Ship {
+        Ship {
+            agent_id: ENEMY,
+            hp: 3,
+            pos_x,
+            pos_y,
+            heading,
+            vel_x,
+            vel_y,
+            friendly: false,
+        }
+    }
+
+    /// Our shot right behind the ship, flying towards it.
+    fn shot_behind(ship: &Ship) -> Shot {
+        Shot {
+            agent_id: OWN,
+            lifetime: 10,
+            pos_x: ship.pos_x - 0.05,
+            pos_y: ship.pos_y,
+            heading: 0.0,
+        }
+    }
+
+    /// Observes the enemy and the shots of each tick next to our ship in the center.
+    fn observe(ticks: u32, track: impl Fn(u32) -> (Ship, Vec<Shot>)) -> OpponentModels {
+        let mut models = OpponentModels::default();
+        let mut own_agent_ids = IdSet::default();
+        own_agent_ids.insert(OWN);
+        let own_ship = Ship {
+            agent_id: OWN,
+            hp: 3,
+            pos_x: 0.5,
+            pos_y: 0.5,
+            friendly: true,
+            ..Ship::default()
+        };
+        for tick in 0..ticks {
+            let (enemy, shots) = track(tick);
+            let world_state = WorldState {
+                tick: Some(tick),
+                ships: vec![own_ship, enemy],
+                shots,
+                ..WorldState::default()
+            };
+            models.observe(&world_state, &own_agent_ids, MAX_VELOCITY, HIT_RADIUS);
+        }
+        models
+    }
+
+    #[test]
+    fn standing_still_is_camping() {
+        let models = observe(60, |_| (enemy(0.8, 0.5, 0.0, 0.0, 0.0), Vec::new()));
+        assert_eq!(models.archetype(ENEMY), Archetype::Camper);
+    }
+
+    #[test]
+    fn flying_at_us_is_chasing() {
+        let models = observe(60, |tick| {
+            let pos_x = 0.9 - MAX_VELOCITY * tick as f32;
+            (
+                enemy(pos_x, 0.5, -MAX_VELOCITY, 0.0, core::f32::consts::PI),
+                Vec::new(),
+            )
+        });
+        assert_eq!(models.archetype(ENEMY), Archetype::Chaser);
+    }
+
+    #[test]
+    fn circling_around_us_is_orbiting() {
+        let radius = 0.2;
+        let models = observe(60, |tick| {
+            let angle = tick as f32 * MAX_VELOCITY / radius;
+            let (sin, cos) = angle.sin_cos();
+            (
+                enemy(
+                    0.5 + radius * cos,
+                    0.5 + radius * sin,
+                    -MAX_VELOCITY * sin,
+                    MAX_VELOCITY * cos,
+                    angle + core::f32::consts::FRAC_PI_2,
+                ),
+                Vec::new(),
+            )
+        });
+        assert_eq!(models.archetype(ENEMY), Archetype::Orbiter);
+    }
+
+    /// Flies away from us and turns back and forth on every tick.
+    fn weaving(tick: u32) -> Ship {
+        let heading = if tick.is_multiple_of(2) { 0.1 } else { -0.1 };
+        enemy(0.6 + 0.004 * tick as f32, 0.5, 0.004, 0.0, heading)
+    }
+
+    #[test]
+    fn turning_under_threat_is_dodging() {
+        let models = observe(60, |tick| {
+            let ship = weaving(tick);
+            let shot = shot_behind(&ship);
+            (ship, vec![shot])
+        });
+        assert_eq!(models.archetype(ENEMY), Archetype::Dodger);
+    }
+
+    #[test]
+    fn turning_without_threat_is_not_dodging() {
+        let models = observe(60, |tick| (weaving(tick), Vec::new()));
+        assert_eq!(models.archetype(ENEMY), Archetype::Unknown);
+    }
+
+    #[test]
+    fn agents_are_classified_after_enough_observations() {
+        let still = |_| (enemy(0.8, 0.5, 0.0, 0.0, 0.0), Vec::new());
+        let models = observe(MIN_OBSERVATIONS - 1, still);
+        assert_eq!(models.archetype(ENEMY), Archetype::Unknown);
+        let models = observe(MIN_OBSERVATIONS, still);
+        assert_eq!(models.archetype(ENEMY), Archetype::Camper);
+    }
+
+    #[test]
+    fn every_shot_is_counted_once() {
+        // a shot every 10 ticks that lives for 10 ticks
+        let models = observe(20, |tick| {
+            let shot = Shot {
+                agent_id: ENEMY,
+                lifetime: 10 - (tick % 10) as i32,
+                pos_x: 0.2,
+                pos_y: 0.2,
+                heading: 0.0,
+            };
+            (enemy(0.8, 0.5, 0.0, 0.0, 0.0), vec![shot])
+        });
+        let profile = models.profiles.get(ENEMY).expect("enemy was observed");
+        assert!(
+            (profile.fire_rate - 0.1).abs() < 1e-6,
+            "{}",
+            profile.fire_rate
+        );
+    }
+}
@@ -0,0 +1,346 @@
+#[cfg(target_arch = "wasm32")]
+use crate::math::Float;
+use crate::{Ship, WorldState, bindings, config::Config, id_map::IdMap, log, numeric, spatial};
+
+/// Number of samples an estimate needs before it is preferred over the configured value.
+const MIN_SAMPLES: u32 = 8;
+
+/// Displacements larger than this, measured the short way across the playfield edges, are
+/// treated as a respawn and are not used as samples.
+const MAX_PLAUSIBLE_STEP: f32 = 0.5;
+
+/// Speeds below this are considered standing still, drag can't be measured from them.
+const MIN_SPEED: f32 = 1e-5;
+
+/// Running statistics over the samples of one physical quantity.
+#[derive(Default, Clone, Copy)]
+pub struct Estimate {
+    samples: u32,
+    mean: f32,
+    max: f32,
+}
+
+impl Estimate {
+    fn add(&mut self, value: f32) {
+        if !value.is_finite() {
+            return;
+        }
+        self.samples += 1;
+        self.mean += (value - self.mean) / self.samples as f32;
+        self.max = self.max.max(value);
+    }
+
+    /// Mean of all samples, `None` while there are not enough samples to trust it.
+    pub fn mean(&self) -> Option<f32> {
+        (self.samples >= MIN_SAMPLES).then_some(self.mean)
+    }
+
+    /// Largest sample seen, `None` while there are not enough samples to trust it.
+    pub fn max(&self) -> Option<f32> {
+        (self.samples >= MIN_SAMPLES).then_some(self.max)
+    }
+}
+
+/// State of a ship on the last observed tick.
+#[derive(Clone, Copy)]
+struct ShipSample {
+    /// tick of the snapshot the sample was taken from
+    tick: Option<u32>,
+    pos_x: f32,
+    pos_y: f32,
+    heading: f32,
+    /// displacement between the tick before and this tick, if that tick was observed
+    velocity: Option<(f32, f32)>,
+    /// action bitmask we issued for this ship on this tick, only known for own ships
+    action: Option<u32>,
+}
+
+#[derive(Clone, Copy)]
+struct ShotSample {
+    /// tick of the snapshot the sample was taken from
+    tick: Option<u32>,
+    lifetime: i32,
+    pos_x: f32,
+    pos_y: f32,
+}
+
+/// Measures the physics of the host by comparing consecutive world state snapshots.
+///
+/// The configured values are used as long as not enough samples are available.
+#[derive(Default)]
+pub struct PhysicsEstimator {
+    ships: IdMap<ShipSample>,
+    shots: IdMap<ShotSample>,
+    /// samples of the snapshot before the last one, their buffers are reused for the next one
+    /// so that observing does not allocate
+    spare_ships: IdMap<ShipSample>,
+    spare_shots: IdMap<ShotSample>,
+    /// actions issued for own ships on the current tick, keyed by agent id
+    actions: IdMap<u32>,
+    /// distance travelled by a ship in one tick
+    pub ship_speed: Estimate,
+    /// heading change of a ship in one tick, in radians
+    pub turn_rate: Estimate,
+    /// velocity gained in one tick while the thrusters are enabled
+    pub thrust_acceleration: Estimate,
+    /// fraction of the velocity lost in one tick while the thrusters are disabled
+    pub drag: Estimate,
+    /// distance travelled by a shot in one tick
+    pub shot_speed: Estimate,
+    /// whether the estimates were already logged
+    calibration_logged: bool,
+}
+
+impl PhysicsEstimator {
+    /// Remembers the action that was issued for an own ship, so that the next snapshot can be
+    /// attributed to it.
+    pub fn record_action(&mut self, agent_id: u32, action: u32) {
+        self.actions.insert(agent_id, action);
+    }
+
+    /// Compares the world state of the tick that just ended with the previous one and updates
+    /// the estimates. Has to be called before the world state is cleared.
+    pub fn observe(&mut self, world_state: &WorldState) {
+        let mut ships = core::mem::take(&mut self.spare_ships);
+        ships.clear();
+        for ship in &world_state.ships {
+            let mut sample = ShipSample {
+                tick: world_state.tick,
+                pos_x: ship.pos_x,
+                pos_y: ship.pos_y,
+                heading: ship.heading,
+                velocity: None,
+                action: self.actions.get(ship.agent_id).copied(),
+            };
+            if let Some(previous) = self
+                .ships
+                .get(ship.agent_id)
+                .copied()
+                .filter(|previous| consecutive(previous.tick, sample.tick))
+            {
+                sample.velocity = self.observe_ship(&previous, &sample);
+            }
+            ships.insert(ship.agent_id, sample);
+        }
+
+        let mut shots = core::mem::take(&mut self.spare_shots);
+        shots.clear();
+        for shot in &world_state.shots {
+            let sample = ShotSample {
+                tick: world_state.tick,
+                lifetime: shot.lifetime,
+                pos_x: shot.pos_x,
+                pos_y: shot.pos_y,
+            };
+            // a shot is only the same shot when its lifetime went down by one, otherwise
+            // the agent fired a new one
+            if let Some(previous) = self.shots.get(shot.agent_id).filter(|previous| {
+                consecutive(previous.tick, sample.tick) && previous.lifetime - 1 == sample.lifetime
+            }) {
+                let (dx, dy) = spatial::wrapped_delta(
+                    (previous.pos_x, previous.pos_y),
+                    (sample.pos_x, sample.pos_y),
+                );
+                let step = (dx.powi(2) + dy.powi(2)).sqrt();
+                if step <= MAX_PLAUSIBLE_STEP {
+                    self.shot_speed.add(step);
+                }
+            }
+            shots.insert(shot.agent_id, sample);
+        }
+
+        self.spare_ships = core::mem::replace(&mut self.ships, ships);
+        self.spare_shots = core::mem::replace(&mut self.shots, shots);
+        self.actions.clear();
+    }
+
+    /// Updates the ship estimates from two consecutive samples of the same ship and returns
+    /// the velocity of the ship on the current tick.
+    fn observe_ship(&mut self, previous: &ShipSample, current: &ShipSample) -> Option<(f32, f32)> {
+        let (vx, vy) = spatial::wrapped_delta(
+            (previous.pos_x, previous.pos_y),
+            (current.pos_x, current.pos_y),
+        );
+        let speed = (vx.powi(2) + vy.powi(2)).sqrt();
+        if speed > MAX_PLAUSIBLE_STEP {
+            return None;
+        }
+        self.ship_speed.add(speed);
+        self.turn_rate
+            .add(numeric::normalize_angle(current.heading - previous.heading).abs());
+
+        // acceleration and drag can only be measured for own ships, because only for them
+        // we know whether the thrusters were enabled
+        if let (Some(action), Some((prev_vx, prev_vy))) = (previous.action, previous.velocity) {
+            let prev_speed = (prev_vx.powi(2) + prev_vy.powi(2)).sqrt();
+            if action & bindings::ActionFlags_ACTION_THRUST == 0 {
+                if prev_speed > MIN_SPEED {
+                    self.drag.add(1.0 - speed / prev_speed);
+                }
+            } else if self.ship_speed.max().is_none_or(|max| speed < max * 0.99) {
+                // samples at max velocity are clamped and would underestimate the acceleration
+                let retained = 1.0 - self.drag();
+                let ax = vx - prev_vx * retained;
+                let ay = vy - prev_vy * retained;
+                self.thrust_acceleration
+                    .add(ax * previous.heading.cos() + ay * previous.heading.sin());
+            }
+        }
+        Some((vx, vy))
+    }
+
+    /// Logs the estimated physics next to the configured values once enough samples are
+    /// available.
+    pub fn log_calibration(&mut self, config: &Config) {
+        if self.calibration_logged
+            || self.ship_speed.max().is_none()
+            || self.shot_speed.mean().is_none()
+        {
+            return;
+        }
+        self.calibration_logged = true;
+        log!(
+            "Physics estimated: ship max velocity {} (config {}), turn rate {} (config {}), thrust acceleration {}, drag {}, shot velocity {} (config {})",
+            self.ship_max_velocity(config),
+            config.ship_max_velocity,
+            self.ship_turn_rate(config).to_degrees(),
+            config.ship_max_turn_rate,
+            self.thrust_acceleration(config),
+            self.drag(),
+            self.shot_velocity(config),
+            config.shot_velocity
+        );
+    }
+
+    /// Sets the velocity of every ship on `tick` to its displacement since the tick before,
+    /// zero for ships that were not observed on exactly that tick.
+    pub fn assign_velocities(&self, ships: &mut [Ship], tick: u32) {
+        for ship in ships {
+            (ship.vel_x, ship.vel_y) = self
+                .ships
+                .get(ship.agent_id)
+                .filter(|previous| consecutive(previous.tick, Some(tick)))
+                .map(|previous| {
+                    spatial::wrapped_delta(
+                        (previous.pos_x, previous.pos_y),
+                        (ship.pos_x, ship.pos_y),
+                    )
+                })
+                .filter(|(vx, vy)| (vx.powi(2) + vy.powi(2)).sqrt() <= MAX_PLAUSIBLE_STEP)
+                .unwrap_or((0.0, 0.0));
+        }
+    }
+
+    /// Maximum distance a ship travels per tick.
+    pub fn ship_max_velocity(&self, config: &Config) -> f32 {
+        self.ship_speed.max().unwrap_or(config.ship_max_velocity)
+    }
+
+    /// Maximum heading change of a ship per tick, in radians.
+    pub fn ship_turn_rate(&self, config: &Config) -> f32 {
+        self.turn_rate
+            .max()
+            .unwrap_or(config.ship_max_turn_rate.to_radians())
+    }
+
+    /// Velocity a ship gains per tick with enabled thrusters. Without observations ships are
+    /// assumed to reach max velocity immediately.
+    pub fn thrust_acceleration(&self, config: &Config) -> f32 {
+        self.thrust_acceleration
+            .mean()
+            .unwrap_or(config.ship_max_velocity)
+    }
+
+    /// Fraction of its velocity a ship loses per tick without thrust. Without observations
+    /// ships are assumed to have no inertia.
+    pub fn drag(&self) -> f32 {
+        self.drag.mean().map_or(1.0, |drag| drag.clamp(0.0, 1.0))
+    }
+
+    /// Distance a shot travels per tick.
+    pub fn shot_velocity(&self, config: &Config) -> f32 {
+        self.shot_speed.mean().unwrap_or(config.shot_velocity)
+    }
+}
+
+/// Whether a sample of `current` directly follows a sample of `previous`.
+fn consecutive(previous: Option<u32>, current: Option<u32>) -> bool {
+    match (previous, current) {
+        (Some(previous), Some(current)) => current == previous.wrapping_add(1),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spatial::PLAYFIELD_SIZE;
+
+    fn ship(pos_x: f32, pos_y: f32) -> Ship {
+        Ship {
+            agent_id: 1,
+            hp: 3,
+            pos_x,
+            pos_y,
+            ..Ship::default()
+        }
+    }
+
+    fn observe(physics: &mut PhysicsEstimator, tick: u32, ship: Ship) {
+        let world_state = WorldState {
+            tick: Some(tick),
+            ships: vec![ship],
+            ..WorldState::default()
+        };
+        physics.observe(&world_state);
+    }
+
+    #[test]
+    fn velocity_is_measured_across_the_edge() {
+        let mut physics = PhysicsEstimator::default();
+        observe(&mut physics, 1, ship(0.998, 0.5));
+        let mut ships = [ship(0.002, 0.5)];
+        physics.assign_velocities(&mut ships, 2);
+        assert!((ships[0].vel_x - 0.004).abs() < 1e-6, "{}", ships[0].vel_x);
+        assert_eq!(ships[0].vel_y, 0.0);
+    }
+
+    #[test]
+    fn velocity_needs_the_tick_before() {
+        let mut physics = PhysicsEstimator::default();
+        observe(&mut physics, 1, ship(0.5, 0.5));
+        let mut ships = [ship(0.51, 0.5)];
+        physics.assign_velocities(&mut ships, 3);
+        assert_eq!((ships[0].vel_x, ships[0].vel_y), (0.0, 0.0));
+    }
+
+    #[test]
+    fn speed_is_sampled_across_the_edge() {
+        let mut physics = PhysicsEstimator::default();
+        for tick in 0..MIN_SAMPLES + 1 {
+            let pos_x = (0.99 + 0.005 * tick as f32).rem_euclid(PLAYFIELD_SIZE);
+            observe(&mut physics, tick, ship(pos_x, 0.5));
+        }
+        let speed = physics.ship_speed.max().expect("every step is a sample");
+        assert!((speed - 0.005).abs() < 1e-5, "{speed}");
+    }
+
+    #[test]
+    fn acceleration_assumes_no_inertia_before_drag_is_measured() {
+        let mut physics = PhysicsEstimator::default();
+        // at max velocity without known actions, then slower with enabled thrusters
+        let mut pos_x = 0.1;
+        for tick in 0..2 * MIN_SAMPLES + 2 {
+            if tick > MIN_SAMPLES {
+                physics.record_action(1, bindings::ActionFlags_ACTION_THRUST);
+                pos_x += 0.003;
+            } else {
+                pos_x += 0.005;
+            }
+            observe(&mut physics, tick, ship(pos_x, 0.5));
+        }
+        assert_eq!(physics.drag(), 1.0);
+        let acceleration = physics.thrust_acceleration(&Config::default());
+        assert!((acceleration - 0.003).abs() < 1e-5, "{acceleration}");
+    }
+}
@@ -0,0 +1,273 @@
+#[cfg(target_arch = "wasm32")]
+use crate::math::Float;
+use crate::{
+    Ship, WorldState, bindings,
+    id_map::{IdMap, IdSet},
+    kinematics::{self, KinematicState, Kinematics},
+    spatial,
+};
+
+/// Movement actions an enemy can take. Firing does not change the movement and is not
+/// predicted.
+pub const MOVEMENT_ACTIONS: [u32; 6] = [
+    bindings::ActionFlags_ACTION_NONE,
+    bindings::ActionFlags_ACTION_THRUST,
+    bindings::ActionFlags_ACTION_TURN_LEFT,
+    bindings::ActionFlags_ACTION_TURN_RIGHT,
+    bindings::ActionFlags_ACTION_THRUST | bindings::ActionFlags_ACTION_TURN_LEFT,
+    bindings::ActionFlags_ACTION_THRUST | bindings::ActionFlags_ACTION_TURN_RIGHT,
+];
+
+/// Weight of the latest inferred action in the moving action frequencies.
+const SMOOTHING: f32 = 0.1;
+
+/// Share of the predicted distribution that goes to repeating the last inferred action, the
+/// rest follows the long term frequencies.
+const REPEAT_WEIGHT: f32 = 0.5;
+
+/// Probability distribution over [`MOVEMENT_ACTIONS`].
+pub type ActionDistribution = [f32; MOVEMENT_ACTIONS.len()];
+
+pub const UNIFORM: ActionDistribution =
+    [1.0 / MOVEMENT_ACTIONS.len() as f32; MOVEMENT_ACTIONS.len()];
+
+/// Inferred actions of one enemy agent.
+struct ActionHistory {
+    last_state: KinematicState,
+    /// index into [`MOVEMENT_ACTIONS`] of the action inferred on the last tick
+    last_action: Option<usize>,
+    /// whether one of our shots was threatening the agent on the last tick
+    threatened: bool,
+    /// action frequencies while not threatened by our shots
+    calm: ActionDistribution,
+    /// action frequencies while threatened by our shots
+    evading: ActionDistribution,
+}
+
+/// Predicts the next actions of enemy agents from the actions inferred from their movement.
+#[derive(Default)]
+pub struct ActionPredictor {
+    histories: IdMap<ActionHistory>,
+}
+
+impl ActionPredictor {
+    /// Infers the action every enemy took on the tick that just ended and updates the action
+    /// frequencies. Has to be called before the world state is cleared.
+    pub fn observe(
+        &mut self,
+        world_state: &WorldState,
+        own_agent_ids: &IdSet,
+        kinematics: &Kinematics,
+        hit_radius: f32,
+    ) {
+        for ship in world_state.ships.iter().filter(|ship| !ship.friendly) {
+            let state = KinematicState::from(ship);
+            let own_shots = world_state
+                .shots
+                .iter()
+                .filter(|shot| own_agent_ids.contains(shot.agent_id));
+            let threatened = kinematics::threatened_by(ship, own_shots, hit_radius);
+
+            let Some(history) = self.histories.get_mut(ship.agent_id) else {
+                self.histories.insert(
+                    ship.agent_id,
+                    ActionHistory {
+                        last_state: state,
+                        last_action: None,
+                        threatened,
+                        calm: UNIFORM,
+                        evading: UNIFORM,
+                    },
+                );
+                continue;
+            };
+
+            let action = infer_action(kinematics, &history.last_state, &state);
+            // the action is the reaction to the situation on the previous tick
+            let frequencies = if history.threatened {
+                &mut history.evading
+            } else {
+                &mut history.calm
+            };
+            for (index, frequency) in frequencies.iter_mut().enumerate() {
+                let sample = if index == action { 1.0 } else { 0.0 };
+                *frequency += (sample - *frequency) * SMOOTHING;
+            }
+            history.last_state = state;
+            history.last_action = Some(action);
+            history.threatened = threatened;
+        }
+    }
+
+    /// Probability of each movement action being the next action of the agent. `threatened`
+    /// selects the behavior while one of our shots is flying at the agent.
+    pub fn distribution(&self, agent_id: u32, threatened: bool) -> ActionDistribution {
+        let Some(history) = self.histories.get(agent_id) else {
+            return UNIFORM;
+        };
+        let mut distribution = if threatened {
+            history.evading
+        } else {
+            history.calm
+        };
+        if let Some(last_action) = history.last_action {
+            for (index, probability) in distribution.iter_mut().enumerate() {
+                let repeat = if index == last_action { 1.0 } else { 0.0 };
+                *probability = *probability * (1.0 - REPEAT_WEIGHT) + repeat * REPEAT_WEIGHT;
+            }
+        }
+        distribution
+    }
+}
+
+/// Probability that a shot fired by `shooter` along its current heading hits `target`,
+/// assuming the target keeps one of the actions of `distribution` until the shot expires. The
+/// shot may hit across the playfield edges.
+pub fn hit_probability(
+    kinematics: &Kinematics,
+    shooter: &KinematicState,
+    target: &Ship,
+    distribution: &ActionDistribution,
+    shot_velocity: f32,
+    shot_lifetime: u32,
+    hit_radius: f32,
+) -> f32 {
+    let target_state = KinematicState::from(target);
+    let horizon = shot_lifetime.min(kinematics::MAX_HORIZON);
+    let (dir_x, dir_y) = (shooter.heading.cos(), shooter.heading.sin());
+    distribution
+        .iter()
+        .zip(MOVEMENT_ACTIONS)
+        .filter(|(_, action)| {
+            let actions = core::iter::repeat_n(*action, horizon as usize);
+            kinematics
+                .simulate(&target_state, actions)
+                .zip(1..)
+                .any(|(state, tick)| {
+                    let travelled = shot_velocity * tick as f32;
+                    let shot_x = shooter.pos_x + travelled * dir_x;
+                    let shot_y = shooter.pos_y + travelled * dir_y;
+                    spatial::wrapped_distance((state.pos_x, state.pos_y), (shot_x, shot_y))
+                        <= hit_radius
+                })
+        })
+        .map(|(probability, _)| probability)
+        .sum()
+}
+
+/// Index into [`MOVEMENT_ACTIONS`] of the action that explains the change from `previous` to
+/// `current` best.
+fn infer_action(
+    kinematics: &Kinematics,
+    previous: &KinematicState,
+    current: &KinematicState,
+) -> usize {
+    let turn_scale = kinematics.turn_rate.max(f32::EPSILON);
+    let velocity_scale = kinematics.max_velocity.max(f32::EPSILON);
+    let error = |action: u32| {
+        let predicted = kinematics.step(previous, action);
+        let heading_error = (predicted.heading - current.heading)
+            .sin()
+            .atan2((predicted.heading - current.heading).cos())
+            .abs();
+        let velocity_error = ((predicted.vel_x - current.vel_x).powi(2)
+            + (predicted.vel_y - current.vel_y).powi(2))
+        .sqrt();
+        heading_error / turn_scale + velocity_error / velocity_scale
+    };
+    MOVEMENT_ACTIONS
+        .iter()
+        .map(|action| error(*action))
+        .enumerate()
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map_or(0, |(index, _)| index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KINEMATICS: Kinematics = Kinematics {
+        turn_rate: 0.1,
+        max_velocity: 0.005,
+        thrust_acceleration: 0.005,
+        drag: 1.0,
+    };
+
+    fn target(pos_x: f32, pos_y: f32) -> Ship {
+        Ship {
+            agent_id: 1,
+            hp: 3,
+            pos_x,
+            pos_y,
+            // facing us, so that no action flies out of the path of the shot
+            heading: core::f32::consts::PI,
+            ..Ship::default()
+        }
+    }
+
+    fn hit_probability_from(shooter_x: f32, target: &Ship) -> f32 {
+        let shooter = KinematicState {
+            pos_x: shooter_x,
+            pos_y: 0.5,
+            ..KinematicState::default()
+        };
+        hit_probability(&KINEMATICS, &shooter, target, &UNIFORM, 0.01, 20, 0.02)
+    }
+
+    #[test]
+    fn shot_hits_across_the_edge() {
+        let inside = hit_probability_from(0.5, &target(0.6, 0.5));
+        let across = hit_probability_from(0.95, &target(0.05, 0.5));
+        assert!(inside > 0.5, "{inside}");
+        assert!((across - inside).abs() < 1e-4, "{across} != {inside}");
+    }
+
+    #[test]
+    fn shot_misses_target_beside_the_path() {
+        assert_eq!(hit_probability_from(0.95, &target(0.05, 0.7)), 0.0);
+    }
+
+    #[test]
+    fn most_frequent_action_is_predicted() {
+        let mut predictor = ActionPredictor::default();
+        let kinematics = Kinematics {
+            drag: 0.0,
+            ..KINEMATICS
+        };
+        // the enemy keeps turning left near the edge, its position wraps around
+        let mut state = KinematicState {
+            pos_x: 0.999,
+            pos_y: 0.5,
+            ..KinematicState::default()
+        };
+        for tick in 0..20 {
+            let world_state = WorldState {
+                tick: Some(tick),
+                ships: vec![Ship {
+                    agent_id: 1,
+                    hp: 3,
+                    pos_x: state.pos_x,
+                    pos_y: state.pos_y,
+                    heading: state.heading,
+                    vel_x: state.vel_x,
+                    vel_y: state.vel_y,
+                    friendly: false,
+                }],
+                ..WorldState::default()
+            };
+            predictor.observe(&world_state, &IdSet::default(), &kinematics, 0.02);
+            state = kinematics.step(&state, bindings::ActionFlags_ACTION_TURN_LEFT);
+        }
+        let distribution = predictor.distribution(1, false);
+        let (most_likely, _) = distribution
+            .iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .expect("distribution is not empty");
+        assert_eq!(
+            MOVEMENT_ACTIONS[most_likely],
+            bindings::ActionFlags_ACTION_TURN_LEFT
+        );
+    }
+}
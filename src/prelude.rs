@@ -0,0 +1,4 @@
+//! Items of the std prelude that the crate uses, imported from `alloc` so that the same code
+//! builds without std on wasm32.
+
+pub use alloc::{boxed::Box, vec, vec::Vec};
@@ -0,0 +1,123 @@
+use core::fmt::{Display, Formatter};
+
+use crate::{
+    Action,
+    behaviors::{self, Blackboard},
+    log,
+};
+
+/// Ticks the agent stays in safe mode before the main strategy decides again.
+pub const COOLDOWN_TICKS: u32 = 60;
+
+/// Decisions in a row on which the budget may run out before the main strategy decided, before
+/// safe mode is entered.
+const MAX_BUDGET_OVERRUNS: u32 = 3;
+
+/// Decisions in a row whose actions may contain conflicting flags before safe mode is entered.
+const MAX_INVALID_ACTIONS: u32 = 3;
+
+/// Why safe mode was entered.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Trigger {
+    /// an export panicked, the state of the main strategy can't be trusted
+    Panic,
+    /// the budget ran out before the main strategy decided on several decisions in a row
+    BudgetExhausted,
+    /// the main strategy requested conflicting flags on several decisions in a row
+    InvalidActions,
+}
+
+impl Display for Trigger {
+    fn fmt(&self, formatter: &mut Formatter) -> core::fmt::Result {
+        formatter.write_str(match self {
+            Self::Panic => "panic",
+            Self::BudgetExhausted => "budget exhausted",
+            Self::InvalidActions => "invalid actions",
+        })
+    }
+}
+
+/// Tracks whether the decisions are made by the main strategy or by the backup policy in
+/// [`safe_action`]. Safe mode is entered on the decision after it was triggered and left once
+/// [`COOLDOWN_TICKS`] passed.
+#[derive(Default)]
+pub struct SafeMode {
+    /// tick on which safe mode was entered, `None` while the main strategy decides
+    entered: Option<u32>,
+    /// why safe mode is entered on the next decision
+    pending: Option<Trigger>,
+    /// decisions in a row on which the budget ran out before the main strategy decided
+    budget_overruns: u32,
+    /// decisions in a row that requested conflicting flags
+    invalid_actions: u32,
+}
+
+impl SafeMode {
+    /// Whether the last decision was made in safe mode.
+    pub fn active(&self) -> bool {
+        self.entered.is_some()
+    }
+
+    /// Enters safe mode on the next decision, unless it is already active.
+    pub fn trigger(&mut self, trigger: Trigger) {
+        if self.entered.is_none() && self.pending.is_none() {
+            log!("safe mode triggered by {trigger}");
+            self.pending = Some(trigger);
+        }
+    }
+
+    /// Enters safe mode on `tick`.
+    pub fn enter(&mut self, tick: u32) {
+        log!("[Tick {tick}] entering safe mode for {COOLDOWN_TICKS} ticks");
+        self.entered = Some(tick);
+        self.pending = None;
+        self.budget_overruns = 0;
+        self.invalid_actions = 0;
+    }
+
+    /// Whether the decision on `tick` is made in safe mode. Enters safe mode when it was
+    /// triggered and leaves it once the cool-down passed.
+    pub fn update(&mut self, tick: u32) -> bool {
+        if self.pending.is_some() {
+            self.enter(tick);
+        }
+        // wrapping, so that ticks that jump back end safe mode instead of extending it
+        if let Some(entered) = self.entered
+            && tick.wrapping_sub(entered) >= COOLDOWN_TICKS
+        {
+            log!("[Tick {tick}] leaving safe mode");
+            self.entered = None;
+        }
+        self.entered.is_some()
+    }
+
+    /// Records a decision of the main strategy and triggers safe mode when it misbehaved too
+    /// often in a row. `decided` is false when the budget ran out before the strategy found an
+    /// action, using up the budget while deciding is fine.
+    pub fn record(&mut self, decided: bool, valid: bool) {
+        self.budget_overruns = if decided { 0 } else { self.budget_overruns + 1 };
+        self.invalid_actions = if valid { 0 } else { self.invalid_actions + 1 };
+        if self.budget_overruns >= MAX_BUDGET_OVERRUNS {
+            self.trigger(Trigger::BudgetExhausted);
+        } else if self.invalid_actions >= MAX_INVALID_ACTIONS {
+            self.trigger(Trigger::InvalidActions);
+        }
+    }
+}
+
+/// Backup policy of safe mode: evades shots that would hit us, fires when a shot would hit a
+/// target and otherwise flies straight. It neither hunts nor aims, so it only depends on the
+/// threats and the target in front of us.
+pub fn safe_action(blackboard: &Blackboard) -> Action {
+    if let Some(evade_action) = blackboard.evade_action {
+        return evade_action;
+    }
+    let fire = behaviors::shot_available(blackboard)
+        && behaviors::target_in_scope(blackboard)
+        && behaviors::line_of_fire_clear(blackboard);
+    Action {
+        turn_direction: None,
+        enable_thrusters: !fire,
+        fire,
+    }
+}
@@ -0,0 +1,278 @@
+#[cfg(target_arch = "wasm32")]
+use crate::math::Float;
+use crate::prelude::*;
+
+/// Side length of the square playfield, positions run from 0 to this and wrap around at the
+/// edges.
+pub const PLAYFIELD_SIZE: f32 = 1.0;
+
+/// Upper bound for the number of cells along one side of the grid.
+const MAX_CELLS_PER_SIDE: usize = 64;
+
+/// Shortest offset from `from` to `to` on the wrapping playfield.
+pub fn wrapped_delta(from: (f32, f32), to: (f32, f32)) -> (f32, f32) {
+    let wrap = |delta: f32| delta - PLAYFIELD_SIZE * (delta / PLAYFIELD_SIZE).round();
+    (wrap(to.0 - from.0), wrap(to.1 - from.1))
+}
+
+/// Shortest distance between `a` and `b` on the wrapping playfield.
+pub fn wrapped_distance(a: (f32, f32), b: (f32, f32)) -> f32 {
+    let (dx, dy) = wrapped_delta(a, b);
+    (dx.powi(2) + dy.powi(2)).sqrt()
+}
+
+/// Uniform grid over the playfield that stores the indices of items (ships or shots) by the cell
+/// they are in, so that queries only look at the cells close to the queried point. The grid
+/// wraps around like the playfield.
+#[derive(Default)]
+pub struct SpatialIndex {
+    cells_per_side: usize,
+    cell_size: f32,
+    /// offset of the first entry of each cell in `entries`, the last offset is the total length
+    cell_starts: Vec<usize>,
+    /// item indices ordered by cell
+    entries: Vec<usize>,
+    positions: Vec<(f32, f32)>,
+    /// cell of each item, only needed while rebuilding
+    cells: Vec<usize>,
+}
+
+impl SpatialIndex {
+    /// Rebuilds the index over the item positions, item `i` is the `i`th position. The grid
+    /// gets about one cell per item. The buffers of the previous build are reused.
+    pub fn rebuild(&mut self, positions: impl IntoIterator<Item = (f32, f32)>) {
+        self.positions.clear();
+        self.positions.extend(positions);
+        self.cells_per_side =
+            ((self.positions.len() as f32).sqrt().ceil() as usize).clamp(1, MAX_CELLS_PER_SIDE);
+        self.cell_size = PLAYFIELD_SIZE / self.cells_per_side as f32;
+
+        // counting sort of the items by their cell
+        self.cells.clear();
+        for index in 0..self.positions.len() {
+            let cell = self.cell_of(self.positions[index]);
+            self.cells.push(cell);
+        }
+        self.cell_starts.clear();
+        self.cell_starts
+            .resize(self.cells_per_side * self.cells_per_side + 1, 0);
+        for &cell in &self.cells {
+            self.cell_starts[cell + 1] += 1;
+        }
+        for cell in 1..self.cell_starts.len() {
+            self.cell_starts[cell] += self.cell_starts[cell - 1];
+        }
+        self.entries.clear();
+        self.entries.resize(self.positions.len(), 0);
+        // the start offsets are used as cursors while filling, afterwards every start offset
+        // points to the start of the next cell
+        for (item, &cell) in self.cells.iter().enumerate() {
+            self.entries[self.cell_starts[cell]] = item;
+            self.cell_starts[cell] += 1;
+        }
+        let cells = self.cell_starts.len() - 1;
+        self.cell_starts.copy_within(..cells, 1);
+        self.cell_starts[0] = 0;
+    }
+
+    /// Items within `radius` of `point`, with their distance to it.
+    pub fn within(
+        &self,
+        point: (f32, f32),
+        radius: f32,
+    ) -> impl Iterator<Item = (usize, f32)> + '_ {
+        let (column, row) = self.coordinates(point);
+        let reach = (radius / self.cell_size)
+            .ceil()
+            .min(self.cells_per_side as f32) as usize;
+        // near the size of the playfield every cell is visited, but only once
+        let (start_column, start_row, span) = if 2 * reach + 1 >= self.cells_per_side {
+            (0, 0, self.cells_per_side)
+        } else {
+            (
+                column as isize - reach as isize,
+                row as isize - reach as isize,
+                2 * reach + 1,
+            )
+        };
+        (0..span)
+            .flat_map(move |dy| (0..span).map(move |dx| (dx, dy)))
+            .flat_map(move |(dx, dy)| {
+                let cell = self.wrapped_cell(start_column + dx as isize, start_row + dy as isize);
+                self.cell_entries(cell)
+            })
+            .filter_map(move |&item| {
+                let distance = wrapped_distance(point, self.positions[item]);
+                (distance <= radius).then_some((item, distance))
+            })
+    }
+
+    /// Writes up to `k` items closest to `point` for which `filter` holds into `found`, ordered
+    /// by distance.
+    pub fn nearest(
+        &self,
+        point: (f32, f32),
+        k: usize,
+        filter: impl Fn(usize) -> bool,
+        found: &mut Vec<(usize, f32)>,
+    ) {
+        found.clear();
+        if k == 0 {
+            return;
+        }
+        let (column, row) = self.coordinates(point);
+        let (column, row) = (column as isize, row as isize);
+        let cells = self.cells_per_side as isize;
+        // search rings of cells around the cell of the point, items outside of ring `r` are at
+        // least `r` cells away
+        for ring in 0..=cells / 2 {
+            // on an even grid the outermost ring reaches the same cells from both sides
+            let last = if 2 * ring >= cells { ring - 1 } else { ring };
+            for dy in -ring..=last {
+                for dx in -ring..=last {
+                    if dx.abs().max(dy.abs()) != ring {
+                        continue;
+                    }
+                    let cell = self.wrapped_cell(column + dx, row + dy);
+                    for &item in self.cell_entries(cell) {
+                        if !filter(item) {
+                            continue;
+                        }
+                        let distance = wrapped_distance(point, self.positions[item]);
+                        // NaN distances are ordered last instead of breaking the order
+                        let position =
+                            found.partition_point(|(_, other)| other.total_cmp(&distance).is_le());
+                        if position < k {
+                            found.insert(position, (item, distance));
+                            found.truncate(k);
+                        }
+                    }
+                }
+            }
+            let covered = ring as f32 * self.cell_size;
+            if found.len() == k
+                && found
+                    .last()
+                    .is_some_and(|(_, distance)| *distance <= covered)
+            {
+                break;
+            }
+        }
+    }
+
+    fn coordinates(&self, (x, y): (f32, f32)) -> (usize, usize) {
+        let coordinate = |value: f32| {
+            // `as` maps NaN to 0, so broken positions still land in a cell
+            ((value.rem_euclid(PLAYFIELD_SIZE) / self.cell_size) as usize)
+                .min(self.cells_per_side - 1)
+        };
+        (coordinate(x), coordinate(y))
+    }
+
+    fn cell_of(&self, point: (f32, f32)) -> usize {
+        let (column, row) = self.coordinates(point);
+        row * self.cells_per_side + column
+    }
+
+    fn wrapped_cell(&self, column: isize, row: isize) -> usize {
+        let cells = self.cells_per_side as isize;
+        (row.rem_euclid(cells) * cells + column.rem_euclid(cells)) as usize
+    }
+
+    fn cell_entries(&self, cell: usize) -> &[usize] {
+        &self.entries[self.cell_starts[cell]..self.cell_starts[cell + 1]]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Pseudo random positions on the playfield, the same for every run.
+    fn positions(count: usize) -> Vec<(f32, f32)> {
+        let mut state = 0x2545_f491_u32;
+        let mut next = || {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            (state >> 8) as f32 / (1 << 24) as f32
+        };
+        (0..count).map(|_| (next(), next())).collect()
+    }
+
+    fn index(positions: &[(f32, f32)]) -> SpatialIndex {
+        let mut index = SpatialIndex::default();
+        index.rebuild(positions.iter().copied());
+        index
+    }
+
+    #[test]
+    fn within_finds_items_across_the_edge() {
+        let index = index(&[(0.99, 0.5), (0.5, 0.5), (0.02, 0.98)]);
+        let found: Vec<_> = index.within((0.01, 0.5), 0.05).collect();
+        assert_eq!(found.len(), 1, "{found:?}");
+        assert_eq!(found[0].0, 0);
+        assert!((found[0].1 - 0.02).abs() < 1e-6);
+        // across both edges at once
+        let found: Vec<_> = index.within((0.99, 0.01), 0.05).collect();
+        assert_eq!(found.len(), 1, "{found:?}");
+        assert_eq!(found[0].0, 2);
+        assert!((found[0].1 - 0.03f32.hypot(0.03)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn nearest_finds_items_across_the_edge() {
+        let index = index(&[(0.5, 0.5), (0.97, 0.03), (0.2, 0.2), (0.7, 0.9)]);
+        let mut found = Vec::new();
+        index.nearest((0.01, 0.99), 2, |_| true, &mut found);
+        let items: Vec<_> = found.iter().map(|&(item, _)| item).collect();
+        assert_eq!(items, [1, 2]);
+        assert!((found[0].1 - 0.04f32.hypot(0.04)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn nearest_skips_filtered_items() {
+        let index = index(&[(0.5, 0.5), (0.52, 0.5), (0.6, 0.5)]);
+        let mut found = Vec::new();
+        index.nearest((0.5, 0.5), 1, |item| item != 0, &mut found);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].0, 1);
+    }
+
+    /// The grid has one cell per item, so these counts give odd and even grids. On even grids
+    /// the outermost ring of the nearest search reaches the same cells from both sides.
+    #[test]
+    fn queries_match_a_full_scan_on_odd_and_even_grids() {
+        for count in [1, 2, 4, 9, 16, 25, 36, 49, 64] {
+            let positions = positions(count);
+            let index = index(&positions);
+            for point in [(0.5, 0.5), (0.0, 0.0), (0.999, 0.001), (0.25, 0.75)] {
+                for radius in [0.05, 0.3, 0.7] {
+                    let mut found: Vec<_> = index.within(point, radius).map(|(i, _)| i).collect();
+                    found.sort_unstable();
+                    let expected: Vec<_> = (0..count)
+                        .filter(|&i| wrapped_distance(point, positions[i]) <= radius)
+                        .collect();
+                    assert_eq!(found, expected, "{count} items, {point:?}, radius {radius}");
+                }
+
+                let k = 3.min(count);
+                let mut found = Vec::new();
+                index.nearest(point, k, |_| true, &mut found);
+                let mut expected: Vec<_> = (0..count)
+                    .map(|i| wrapped_distance(point, positions[i]))
+                    .collect();
+                expected.sort_by(f32::total_cmp);
+                let distances: Vec<_> = found.iter().map(|&(_, distance)| distance).collect();
+                assert_eq!(distances, expected[..k], "{count} items, {point:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn within_visits_every_item_once_for_large_radii() {
+        let positions = positions(16);
+        let index = index(&positions);
+        assert_eq!(index.within((0.3, 0.6), 2.0).count(), 16);
+    }
+}
@@ -0,0 +1,196 @@
+use alloc::collections::VecDeque;
+use core::fmt::{Display, Formatter};
+
+use crate::{
+    TurnDirection,
+    behaviors::{self, Blackboard},
+    log,
+};
+
+/// Number of transitions that are kept per ship.
+const TRANSITION_LOG_LENGTH: usize = 32;
+
+/// Targets closer than this multiple of the fire range are aimed at instead of hunted.
+const AIM_RANGE: f32 = 1.5;
+
+/// Explicit mode of one own ship.
+#[derive(Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ShipMode {
+    /// no target left
+    #[default]
+    Idle,
+    /// flying towards a target that is out of range
+    Hunting,
+    /// turning towards a target in range
+    Aiming,
+    /// a shot fired now would hit
+    Firing,
+    /// our shot is still flying, so we can't fire
+    Reloading,
+    /// a shot would hit us
+    Evading,
+    /// low on hp and unable to fire while a target is close
+    Retreating,
+}
+
+impl ShipMode {
+    pub const ALL: [Self; 7] = [
+        Self::Idle,
+        Self::Hunting,
+        Self::Aiming,
+        Self::Firing,
+        Self::Reloading,
+        Self::Evading,
+        Self::Retreating,
+    ];
+}
+
+impl Display for ShipMode {
+    fn fmt(&self, formatter: &mut Formatter) -> Result<(), core::fmt::Error> {
+        let str = match self {
+            Self::Idle => "idle",
+            Self::Hunting => "hunting",
+            Self::Aiming => "aiming",
+            Self::Firing => "firing",
+            Self::Reloading => "reloading",
+            Self::Evading => "evading",
+            Self::Retreating => "retreating",
+        };
+        write!(formatter, "{str}")
+    }
+}
+
+/// A change of the mode of a ship.
+#[derive(Clone, Copy)]
+pub struct Transition {
+    pub tick: u32,
+    pub from: ShipMode,
+    pub to: ShipMode,
+    pub reason: &'static str,
+}
+
+impl Display for Transition {
+    fn fmt(&self, formatter: &mut Formatter) -> Result<(), core::fmt::Error> {
+        write!(
+            formatter,
+            "[Tick {}] mode {} -> {}: {}",
+            self.tick, self.from, self.to, self.reason
+        )
+    }
+}
+
+/// Mode of one own ship, with the transitions that led to it. The mode replaces the implicit
+/// state of whether our shot is available and whether a shot would hit us: the behavior tree
+/// decides by it which behavior runs.
+pub struct ModeMachine {
+    mode: ShipMode,
+    transitions: VecDeque<Transition>,
+    /// highest hp our ship had so far, used to tell when the hp are low
+    max_hp: i32,
+    /// turn direction chosen when evading started, kept until the evasion is over so that we
+    /// don't flip between both sides
+    evade_direction: Option<TurnDirection>,
+}
+
+impl Default for ModeMachine {
+    fn default() -> Self {
+        Self {
+            mode: ShipMode::default(),
+            // the log never grows beyond its length, so it is allocated only once
+            transitions: VecDeque::with_capacity(TRANSITION_LOG_LENGTH),
+            max_hp: 0,
+            evade_direction: None,
+        }
+    }
+}
+
+impl ModeMachine {
+    pub fn mode(&self) -> ShipMode {
+        self.mode
+    }
+
+    /// The last transitions, oldest first.
+    pub fn transitions(&self) -> impl Iterator<Item = &Transition> {
+        self.transitions.iter()
+    }
+
+    /// Evaluates the transition conditions of the current mode, switches to the next mode if
+    /// one applies, and writes the resulting mode to the blackboard.
+    pub fn update(&mut self, blackboard: &mut Blackboard) {
+        self.max_hp = self.max_hp.max(blackboard.own_hp);
+        let (next, reason) = self.next_mode(blackboard);
+        if next != self.mode {
+            self.transition(blackboard, next, reason);
+        }
+        if let Some(evade_action) = &mut blackboard.evade_action
+            && self.evade_direction.is_some()
+        {
+            evade_action.turn_direction = self.evade_direction;
+        }
+        blackboard.mode = self.mode;
+    }
+
+    /// Mode the ship should be in, with the reason for it. Firing takes priority over evading
+    /// because turning away would make the shot miss.
+    fn next_mode(&self, blackboard: &Blackboard) -> (ShipMode, &'static str) {
+        let Some(aim) = &blackboard.aim else {
+            if blackboard.evade_action.is_some() {
+                return (ShipMode::Evading, "shot incoming");
+            }
+            return (ShipMode::Idle, "no target");
+        };
+        if behaviors::shot_available(blackboard)
+            && behaviors::target_in_scope(blackboard)
+            && behaviors::line_of_fire_clear(blackboard)
+        {
+            return (ShipMode::Firing, "target in scope");
+        }
+        if blackboard.evade_action.is_some() {
+            return (ShipMode::Evading, "shot incoming");
+        }
+        if !blackboard.shot_available {
+            let low_hp = blackboard.own_hp <= self.max_hp / 2 && self.max_hp > 1;
+            if low_hp && aim.distance <= aim.fire_range {
+                return (ShipMode::Retreating, "low hp and target close");
+            }
+            return (ShipMode::Reloading, "shot in flight");
+        }
+        if aim.distance <= aim.fire_range * AIM_RANGE {
+            (ShipMode::Aiming, "target in range")
+        } else {
+            (ShipMode::Hunting, "target out of range")
+        }
+    }
+
+    fn transition(&mut self, blackboard: &Blackboard, to: ShipMode, reason: &'static str) {
+        let from = self.mode;
+        self.on_exit(from);
+        let transition = Transition {
+            tick: blackboard.tick,
+            from,
+            to,
+            reason,
+        };
+        log!("Agent: {}, {transition}", blackboard.own_agent_id);
+        if self.transitions.len() == TRANSITION_LOG_LENGTH {
+            self.transitions.pop_front();
+        }
+        self.transitions.push_back(transition);
+        self.mode = to;
+        self.on_enter(to, blackboard);
+    }
+
+    fn on_enter(&mut self, mode: ShipMode, blackboard: &Blackboard) {
+        if mode == ShipMode::Evading {
+            self.evade_direction = blackboard
+                .evade_action
+                .and_then(|action| action.turn_direction);
+        }
+    }
+
+    fn on_exit(&mut self, mode: ShipMode) {
+        if mode == ShipMode::Evading {
+            self.evade_direction = None;
+        }
+    }
+}
@@ -0,0 +1,252 @@
+use core::fmt::{Display, Formatter};
+
+#[cfg(target_arch = "wasm32")]
+use crate::math::Float;
+use crate::{
+    Action, TurnDirection, action::ActionBuilder, behaviors::Blackboard, collision, kinematics,
+    spatial,
+};
+
+/// Clearance to a shot (in hit radii) from which on a shot is no danger anymore.
+const SAFE_CLEARANCE_RADII: f32 = 4.0;
+
+/// Share of the fire range at which we want to stay to the target.
+const PREFERRED_RANGE: f32 = 0.8;
+
+/// One aspect of the situation that a candidate action is scored by.
+pub struct Consideration {
+    pub name: &'static str,
+    pub weight: f32,
+    /// rates a candidate action between 0 (bad) and 1 (good)
+    pub score: fn(&Blackboard, Action) -> f32,
+}
+
+/// Considerations used to score the candidate actions, dodging shots weighs the most.
+pub const CONSIDERATIONS: [Consideration; 5] = [
+    Consideration {
+        name: "threat clearance",
+        weight: 4.0,
+        score: threat_clearance,
+    },
+    Consideration {
+        name: "shot availability",
+        weight: 3.0,
+        score: shot_availability,
+    },
+    Consideration {
+        name: "aim error",
+        weight: 2.0,
+        score: aim_error,
+    },
+    Consideration {
+        name: "ally spacing",
+        weight: 1.5,
+        score: ally_spacing,
+    },
+    Consideration {
+        name: "distance to target",
+        weight: 1.0,
+        score: distance_to_target,
+    },
+];
+
+/// Weighted scores of one candidate action.
+pub struct Rating {
+    pub action: Action,
+    pub total: f32,
+    /// weighted score of each entry of [`CONSIDERATIONS`]
+    pub breakdown: [f32; CONSIDERATIONS.len()],
+}
+
+impl Display for Rating {
+    fn fmt(&self, formatter: &mut Formatter) -> Result<(), core::fmt::Error> {
+        write!(formatter, "total {}", self.total)?;
+        for (consideration, score) in CONSIDERATIONS.iter().zip(self.breakdown) {
+            write!(formatter, ", {} {}", consideration.name, score)?;
+        }
+        Ok(())
+    }
+}
+
+/// Number of actions yielded by [`candidate_actions`].
+pub const CANDIDATE_ACTIONS: usize = 8;
+
+/// All actions that are sent to the host unchanged, see [`ActionBuilder`].
+///
+/// The four flags form 16 combinations. The 4 that turn left and right at once are invalid,
+/// which leaves 12 valid ones. Of those, the 4 that turn while firing reach the host
+/// without the turn, so they are the same action as firing without turning and rating them
+/// again would only spend budget. That leaves these 8.
+pub fn candidate_actions() -> impl Iterator<Item = Action> {
+    [None, Some(TurnDirection::Left), Some(TurnDirection::Right)]
+        .into_iter()
+        .flat_map(|turn_direction| {
+            [false, true].into_iter().flat_map(move |enable_thrusters| {
+                [false, true].map(move |fire| Action {
+                    enable_thrusters,
+                    turn_direction,
+                    fire,
+                })
+            })
+        })
+        .filter(|&action| {
+            ActionBuilder::new()
+                .request(action)
+                .build()
+                .dropped()
+                .next()
+                .is_none()
+        })
+}
+
+/// Scores `action` by all considerations.
+pub fn rate(blackboard: &Blackboard, action: Action) -> Rating {
+    let mut breakdown = [0.0; CONSIDERATIONS.len()];
+    for (weighted, consideration) in breakdown.iter_mut().zip(&CONSIDERATIONS) {
+        *weighted =
+            consideration.weight * (consideration.score)(blackboard, action).clamp(0.0, 1.0);
+    }
+    Rating {
+        action,
+        total: breakdown.iter().sum(),
+        breakdown,
+    }
+}
+
+/// Rates the candidate actions for which `allowed` holds while the budget lasts and returns the
+/// best one rated, `None` when the budget did not suffice for a single rating.
+pub fn choose(blackboard: &mut Blackboard, allowed: impl Fn(Action) -> bool) -> Option<Rating> {
+    let cost = rating_cost(blackboard);
+    let mut best: Option<Rating> = None;
+    for action in candidate_actions().filter(|&action| allowed(action)) {
+        if !blackboard.budget.try_spend(cost) {
+            break;
+        }
+        let rating = rate(blackboard, action);
+        if best.as_ref().is_none_or(|best| rating.total > best.total) {
+            best = Some(rating);
+        }
+    }
+    best
+}
+
+/// Work units of rating one action, dominated by the simulated paths.
+fn rating_cost(blackboard: &Blackboard) -> u32 {
+    let threats = blackboard.threats.len() as u32 * kinematics::MAX_HORIZON;
+    // the distance to the target and the aim error each simulate one tick
+    let ships = blackboard.ships.len() as u32 * collision::COLLISION_HORIZON + 2;
+    threats + ships
+}
+
+/// How far the predicted path stays away from the shots that would hit us. Shots passing within
+/// the evade radius count as hits, so the clearance is only safe beyond it.
+fn threat_clearance(blackboard: &Blackboard, action: Action) -> f32 {
+    let safe_clearance =
+        (SAFE_CLEARANCE_RADII * blackboard.hit_radius).max(blackboard.evade_radius());
+    if safe_clearance <= 0.0 {
+        return 1.0;
+    }
+    blackboard
+        .threats
+        .iter()
+        .map(|&shot| {
+            blackboard.kinematics.clearance(
+                &blackboard.own_state,
+                action.into(),
+                blackboard.analysis.shot_path(shot),
+            )
+        })
+        .fold(safe_clearance, f32::min)
+        / safe_clearance
+}
+
+/// Firing is only worth it when the shot would hit and no ally is in the way, and the shot
+/// should not be distorted by turning. Not firing is neutral.
+fn shot_availability(blackboard: &Blackboard, action: Action) -> f32 {
+    if !action.fire {
+        return 0.5;
+    }
+    let ready = blackboard.shot_available
+        && action.turn_direction.is_none()
+        && blackboard
+            .aim
+            .as_ref()
+            .is_some_and(|aim| aim.on_target && aim.distance <= aim.fire_range)
+        && blackboard.line_of_fire_clear();
+    if ready { 1.0 } else { 0.0 }
+}
+
+/// How close the heading after the action is to the direction of the target.
+fn aim_error(blackboard: &Blackboard, action: Action) -> f32 {
+    let Some(aim) = &blackboard.aim else {
+        return 0.5;
+    };
+    let next = blackboard
+        .kinematics
+        .step(&blackboard.own_state, action.into());
+    let (dx, dy) = spatial::wrapped_delta(
+        (next.pos_x, next.pos_y),
+        (aim.target.pos_x, aim.target.pos_y),
+    );
+    let target_angle = dy.atan2(dx);
+    let error = (target_angle - next.heading)
+        .sin()
+        .atan2((target_angle - next.heading).cos());
+    1.0 - error.abs() / core::f32::consts::PI
+}
+
+/// How well the action keeps the distance to other ships.
+fn ally_spacing(blackboard: &Blackboard, action: Action) -> f32 {
+    let margin = collision::collision_margin(
+        &blackboard.kinematics,
+        &blackboard.own_state,
+        blackboard.own_agent_id,
+        blackboard.ships,
+        action.into(),
+        blackboard.hit_radius,
+    );
+    if margin >= 0.0 || blackboard.hit_radius <= 0.0 {
+        1.0
+    } else {
+        1.0 + margin / (2.0 * blackboard.hit_radius)
+    }
+}
+
+/// How close the distance to the target after the action is to the preferred firing distance.
+fn distance_to_target(blackboard: &Blackboard, action: Action) -> f32 {
+    let Some(aim) = &blackboard.aim else {
+        return 0.5;
+    };
+    let preferred = aim.fire_range * PREFERRED_RANGE;
+    let next = blackboard
+        .kinematics
+        .step(&blackboard.own_state, action.into());
+    let distance = spatial::wrapped_distance(
+        (next.pos_x, next.pos_y),
+        (aim.target.pos_x, aim.target.pos_y),
+    );
+    1.0 - (distance - preferred).abs() / preferred
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bindings;
+
+    /// Every combination of the flags that the host accepts is rated as some candidate.
+    #[test]
+    fn candidates_cover_every_valid_action() {
+        let candidates: Vec<u32> = candidate_actions().map(u32::from).collect();
+        assert_eq!(candidates.len(), CANDIDATE_ACTIONS);
+        for bits in 0..16 {
+            let resolved = ActionBuilder::new().request_bits(bits).build();
+            let both_turns =
+                bindings::ActionFlags_ACTION_TURN_LEFT | bindings::ActionFlags_ACTION_TURN_RIGHT;
+            if bits & both_turns == both_turns {
+                assert!(!resolved.valid(), "{bits:#b}");
+            } else {
+                assert!(candidates.contains(&resolved.bits()), "{bits:#b}");
+            }
+        }
+    }
+}
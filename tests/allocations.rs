@@ -0,0 +1,133 @@
+//! Deciding on an action must not allocate once the buffers of the agent have grown to the size
+//! of the match, not even while its log messages are formatted.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+};
+
+use quick_start_simple::{
+    Context, clear_world_state, init_agent, make_action, set_config_parameter, set_native_log,
+    update_ship, update_shot,
+};
+
+thread_local! {
+    /// allocations made by the current thread, tests run in parallel on their own threads
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    /// log messages received by the current thread
+    static LOGGED: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Counts the message instead of printing it, the test harness allocates to capture output.
+fn count_message(_message: &str) {
+    LOGGED.with(|logged| logged.set(logged.get() + 1));
+}
+
+struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|allocations| allocations.set(allocations.get() + 1));
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|allocations| allocations.set(allocations.get() + 1));
+        unsafe { System.alloc_zeroed(layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.with(|allocations| allocations.set(allocations.get() + 1));
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+const OWN_AGENTS: [u32; 4] = [0, 1, 2, 3];
+const ENEMY_AGENTS: [u32; 4] = [4, 5, 6, 7];
+
+/// Feeds the world state of `tick` to the agent: all ships circle around their start position
+/// and every agent fires a shot that lives for 20 ticks every 20 ticks.
+fn update_world(ctx: &mut Context, tick: u32) {
+    clear_world_state(ctx);
+    for agent_id in OWN_AGENTS.into_iter().chain(ENEMY_AGENTS) {
+        let angle = tick as f32 * 0.05 + agent_id as f32;
+        let pos_x = 0.1 + 0.1 * agent_id as f32 + 0.02 * angle.cos();
+        let pos_y = 0.5 + 0.02 * angle.sin();
+        let heading = (tick * 3 + agent_id * 45) as f32 % 360.0;
+        update_ship(ctx, agent_id, 3, pos_x, pos_y, heading);
+
+        let age = (tick + agent_id) % 20;
+        let travelled = 0.01 * age as f32;
+        update_shot(
+            ctx,
+            agent_id,
+            20 - age as i32,
+            pos_x + travelled,
+            pos_y,
+            90.0,
+        );
+    }
+}
+
+/// Agent whose buffers grew to the size of the match.
+fn warmed_up_agent() -> Box<Context> {
+    set_native_log(Some(count_message));
+    let mut ctx = init_agent(8, 1, 42);
+    for (param, value) in [(0, 10.0), (1, 0.005), (2, 0.02), (3, 0.01), (4, 20.0)] {
+        set_config_parameter(&mut ctx, param, value);
+    }
+
+    // the buffers grow during the first ticks, the match repeats every 20 ticks afterwards
+    for tick in 0..100 {
+        update_world(&mut ctx, tick);
+        for agent_id in OWN_AGENTS {
+            make_action(&mut ctx, agent_id, tick);
+        }
+    }
+    ctx
+}
+
+#[test]
+fn make_action_does_not_allocate_in_steady_state() {
+    let mut ctx = warmed_up_agent();
+
+    let logged = LOGGED.with(Cell::get);
+    for tick in 100..200 {
+        update_world(&mut ctx, tick);
+        for agent_id in OWN_AGENTS {
+            let before = ALLOCATIONS.with(Cell::get);
+            make_action(&mut ctx, agent_id, tick);
+            let allocations = ALLOCATIONS.with(Cell::get) - before;
+            assert_eq!(
+                allocations, 0,
+                "make_action of agent {agent_id} allocated {allocations} times on tick {tick}"
+            );
+        }
+    }
+    assert!(LOGGED.with(Cell::get) > logged, "nothing was logged");
+}
+
+/// The bump allocator of the wasm build only reuses freed memory that was the last allocation,
+/// so a whole tick, including the world state updates, must not allocate either.
+#[test]
+fn tick_does_not_allocate_in_steady_state() {
+    let mut ctx = warmed_up_agent();
+    let logged = LOGGED.with(Cell::get);
+    for tick in 100..200 {
+        let before = ALLOCATIONS.with(Cell::get);
+        update_world(&mut ctx, tick);
+        for agent_id in OWN_AGENTS {
+            make_action(&mut ctx, agent_id, tick);
+        }
+        let allocations = ALLOCATIONS.with(Cell::get) - before;
+        assert_eq!(allocations, 0, "tick {tick} allocated {allocations} times");
+    }
+    assert!(LOGGED.with(Cell::get) > logged, "nothing was logged");
+}
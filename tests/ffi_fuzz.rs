@@ -0,0 +1,484 @@
+//! Feeds random sequences of calls with extreme values through the exported functions and checks
+//! that the agent neither panics nor hangs and only returns valid actions.
+//!
+//! Every case is generated from its seed, a failing case is reported with its seed and the calls
+//! that led to the failure. The exported functions catch panics and mark the context as
+//! degraded, which fails the case as well, the seed is printed when the panic is logged.
+//!
+//! Panics can't be caught on wasm32, they trap the instance. The same cases are therefore also
+//! played on the wasm module with node, when the wasm32 target and node are installed.
+
+use std::{
+    cell::Cell,
+    env,
+    fmt::{Debug, Formatter},
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Output},
+    sync::{
+        Arc, Once, OnceLock,
+        atomic::{AtomicU32, Ordering},
+        mpsc,
+    },
+    thread,
+    time::Duration,
+};
+
+use quick_start_simple::{
+    Context, clear_world_state, free_context, init_agent, make_action, set_config_parameter,
+    update_score, update_ship, update_shot,
+};
+
+/// Bits of all action flags, see `ActionFlags` in `scubywasm_agent.h`.
+const ACTION_MASK: u32 = 0b1111;
+const TURN_LEFT: u32 = 2;
+const TURN_RIGHT: u32 = 4;
+
+/// Number of generated cases per test.
+const CASES: u32 = 200;
+
+/// Number of calls in each case.
+const CALLS_PER_CASE: usize = 120;
+
+/// Time all cases of a test may take before the agent is assumed to hang.
+const TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Floats that break naive arithmetic.
+const EXTREME_FLOATS: [f32; 15] = [
+    f32::NAN,
+    f32::INFINITY,
+    f32::NEG_INFINITY,
+    f32::MAX,
+    f32::MIN,
+    1e30,
+    -1e30,
+    1e-30,
+    -1e-30,
+    f32::MIN_POSITIVE,
+    -f32::MIN_POSITIVE,
+    1e7,
+    -1e7,
+    720.5,
+    -360.0,
+];
+
+/// Plays the cases of a file on the wasm module given as first argument and prints every case
+/// that traps or returns an invalid action. Each case starts with a line `case <seed>` and has
+/// one call per line, formatted like [`Call`]. The optional third argument limits the memory of
+/// the module to that many pages more than it starts with.
+const WASM_DRIVER: &str = r#"
+const fs = require('fs');
+const [modulePath, casesPath, extraPages] = process.argv.slice(2);
+
+function leb(bytes, offset) {
+    let value = 0, shift = 0, byte;
+    do {
+        byte = bytes[offset++];
+        value += (byte & 0x7f) * 2 ** shift;
+        shift += 7;
+    } while (byte & 0x80);
+    return [value, offset];
+}
+
+function toLeb(value) {
+    const bytes = [];
+    do {
+        let byte = value & 0x7f;
+        value = Math.floor(value / 128);
+        bytes.push(value ? byte | 0x80 : byte);
+    } while (value);
+    return bytes;
+}
+
+// rewrites the memory section, so that the memory can only grow by `extra` pages
+function limitMemory(bytes, extra) {
+    const out = [...bytes.subarray(0, 8)];
+    let offset = 8;
+    while (offset < bytes.length) {
+        const id = bytes[offset];
+        const [length, start] = leb(bytes, offset + 1);
+        let content = [...bytes.subarray(start, start + length)];
+        if (id === 5) {
+            const [min] = leb(content, 2);
+            content = [1, 1, ...toLeb(min), ...toLeb(min + extra)];
+        }
+        out.push(id, ...toLeb(content.length), ...content);
+        offset = start + length;
+    }
+    return Uint8Array.from(out);
+}
+
+const value = text => ({ NaN: NaN, inf: Infinity, '-inf': -Infinity })[text] ?? Number(text);
+
+let bytes = fs.readFileSync(modulePath);
+if (extraPages !== undefined) {
+    bytes = limitMemory(bytes, Number(extraPages));
+}
+const wasmModule = new WebAssembly.Module(bytes);
+const decoder = new TextDecoder();
+
+function play(seed, calls) {
+    let exports;
+    let log = '';
+    const imports = {
+        debug: {
+            debug_log(ptr, len) {
+                log = decoder.decode(new Uint8Array(exports.memory.buffer, ptr, len));
+            },
+        },
+    };
+    exports = new WebAssembly.Instance(wasmModule, imports).exports;
+    let call = `init_agent(8, 1, ${seed})`;
+    try {
+        const ctx = exports.init_agent(8, 1, seed);
+        for (call of calls) {
+            const [, name, args] = call.match(/^(\w+)\(ctx(?:, (.*))?\)$/);
+            const action = exports[name](ctx, ...(args ? args.split(', ').map(value) : [])) >>> 0;
+            if (name === 'make_action' && (action & ~0b1111 || (action & 0b110) === 0b110)) {
+                console.log(`case ${seed}: ${call} returned ${action.toString(2)}`);
+                return false;
+            }
+        }
+        exports.free_context(ctx);
+    } catch (error) {
+        if (!(error instanceof WebAssembly.RuntimeError)) {
+            throw error;
+        }
+        console.log(`case ${seed}: ${call} trapped with ${error.message}, last log: ${log}`);
+        return false;
+    }
+    return true;
+}
+
+let passed = true;
+for (const [, seed, calls] of fs.readFileSync(casesPath, 'utf8').matchAll(/^case (\d+)\n([^]*?)(?=^case |(?![^]))/gm)) {
+    passed = play(Number(seed), calls.split('\n').filter(call => call)) && passed;
+}
+process.exit(passed ? 0 : 1);
+"#;
+
+thread_local! {
+    /// case that is played on the current thread, reported when it panics
+    static CURRENT_CASE: Cell<Option<u32>> = const { Cell::new(None) };
+}
+
+/// Small deterministic random number generator, so that every case can be reproduced from its
+/// seed.
+struct Random(u64);
+
+impl Random {
+    fn new(seed: u32) -> Self {
+        // the state must not be zero
+        Self(u64::from(seed) << 1 | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, bound: u64) -> u64 {
+        self.next() % bound
+    }
+
+    fn chance(&mut self, percent: u64) -> bool {
+        self.below(100) < percent
+    }
+
+    fn pick<T: Copy>(&mut self, values: &[T]) -> T {
+        values[self.below(values.len() as u64) as usize]
+    }
+
+    /// Mostly values on the playfield, sometimes zero or values just outside of it and, when
+    /// `extreme`, values that break naive arithmetic.
+    fn float(&mut self, extreme: bool) -> f32 {
+        if extreme && self.chance(20) {
+            self.pick(&EXTREME_FLOATS)
+        } else if self.chance(10) {
+            self.pick(&[0.0, -0.0, -0.5, 1.5])
+        } else {
+            self.below(1 << 24) as f32 / (1 << 24) as f32
+        }
+    }
+
+    fn int(&mut self) -> i32 {
+        if self.chance(75) {
+            self.below(60) as i32 - 5
+        } else {
+            self.pick(&[i32::MIN, -1, 0, 1, i32::MAX])
+        }
+    }
+
+    fn agent_id(&mut self) -> u32 {
+        if self.chance(85) {
+            self.below(8) as u32
+        } else {
+            let huge = self.next() as u32;
+            self.pick(&[u32::MAX, u32::MAX - 1, 1 << 31, huge])
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Call {
+    SetConfigParameter(u32, f32),
+    ClearWorldState,
+    UpdateShip(u32, i32, f32, f32, f32),
+    UpdateShot(u32, i32, f32, f32, f32),
+    UpdateScore(u32, i32),
+    MakeAction(u32, u32),
+}
+
+impl Debug for Call {
+    fn fmt(&self, formatter: &mut Formatter) -> std::fmt::Result {
+        match *self {
+            Self::SetConfigParameter(param, value) => {
+                write!(formatter, "set_config_parameter(ctx, {param}, {value:?})")
+            }
+            Self::ClearWorldState => write!(formatter, "clear_world_state(ctx)"),
+            Self::UpdateShip(agent_id, hp, x, y, heading) => write!(
+                formatter,
+                "update_ship(ctx, {agent_id}, {hp}, {x:?}, {y:?}, {heading:?})"
+            ),
+            Self::UpdateShot(agent_id, lifetime, x, y, heading) => write!(
+                formatter,
+                "update_shot(ctx, {agent_id}, {lifetime}, {x:?}, {y:?}, {heading:?})"
+            ),
+            Self::UpdateScore(agent_id, score) => {
+                write!(formatter, "update_score(ctx, {agent_id}, {score})")
+            }
+            Self::MakeAction(agent_id, tick) => {
+                write!(formatter, "make_action(ctx, {agent_id}, {tick})")
+            }
+        }
+    }
+}
+
+/// Generates the calls of the case `seed`. The calls roughly follow the order of the host, so
+/// that the decisions get to see populated world states.
+fn generate(seed: u32, extreme: bool) -> Vec<Call> {
+    let mut random = Random::new(seed);
+    let mut calls = Vec::with_capacity(CALLS_PER_CASE);
+    let mut tick: u32 = 0;
+    while calls.len() < CALLS_PER_CASE {
+        let call = match random.below(10) {
+            0 => {
+                let param = if random.chance(80) {
+                    random.below(5) as u32
+                } else {
+                    random.pick(&[5, u32::MAX])
+                };
+                Call::SetConfigParameter(param, random.float(extreme))
+            }
+            1 => Call::ClearWorldState,
+            2..=4 => Call::UpdateShip(
+                random.agent_id(),
+                random.int(),
+                random.float(extreme),
+                random.float(extreme),
+                random.float(extreme) * 360.0,
+            ),
+            5 | 6 => Call::UpdateShot(
+                random.agent_id(),
+                random.int(),
+                random.float(extreme),
+                random.float(extreme),
+                random.float(extreme) * 360.0,
+            ),
+            7 => Call::UpdateScore(random.agent_id(), random.int()),
+            _ => {
+                tick = if random.chance(90) {
+                    tick.wrapping_add(1)
+                } else {
+                    let any = random.next() as u32;
+                    random.pick(&[0, u32::MAX, any])
+                };
+                Call::MakeAction(random.agent_id(), tick)
+            }
+        };
+        calls.push(call);
+    }
+    calls
+}
+
+/// Plays the calls on a fresh agent, stops at the first caught panic or invalid action.
+fn play(calls: &[Call], seed: u32) -> Result<(), String> {
+    let mut ctx: Box<Context> = init_agent(8, 1, seed);
+    for (index, &call) in calls.iter().enumerate() {
+        match call {
+            Call::SetConfigParameter(param, value) => set_config_parameter(&mut ctx, param, value),
+            Call::ClearWorldState => clear_world_state(&mut ctx),
+            Call::UpdateShip(agent_id, hp, x, y, heading) => {
+                update_ship(&mut ctx, agent_id, hp, x, y, heading)
+            }
+            Call::UpdateShot(agent_id, lifetime, x, y, heading) => {
+                update_shot(&mut ctx, agent_id, lifetime, x, y, heading)
+            }
+            Call::UpdateScore(agent_id, score) => update_score(&mut ctx, agent_id, score),
+            Call::MakeAction(agent_id, tick) => {
+                let action = make_action(&mut ctx, agent_id, tick);
+                if action & !ACTION_MASK != 0 {
+                    return Err(format!("call {index} returned unknown flags: {action:#b}"));
+                }
+                if action & TURN_LEFT != 0 && action & TURN_RIGHT != 0 {
+                    return Err(format!("call {index} turns left and right: {action:#b}"));
+                }
+            }
+        }
+        if ctx.degraded() {
+            return Err(format!("call {index} panicked"));
+        }
+    }
+    free_context(&mut ctx);
+    Ok(())
+}
+
+/// Plays all cases on another thread, so that a hanging agent fails the test instead of blocking
+/// it, and reports the first failing case.
+fn fuzz(extreme: bool) {
+    static REPORT_CASE: Once = Once::new();
+    REPORT_CASE.call_once(|| {
+        let default_hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            if let Some(seed) = CURRENT_CASE.get() {
+                eprintln!("case {seed} panicked");
+            }
+            default_hook(info);
+        }));
+    });
+
+    let current_seed = Arc::new(AtomicU32::new(0));
+    let (sender, receiver) = mpsc::channel();
+    let seed_of_worker = Arc::clone(&current_seed);
+    thread::spawn(move || {
+        let failure = (0..CASES).find_map(|seed| {
+            seed_of_worker.store(seed, Ordering::Relaxed);
+            CURRENT_CASE.set(Some(seed));
+            let calls = generate(seed, extreme);
+            play(&calls, seed).err().map(|error| (seed, error, calls))
+        });
+        CURRENT_CASE.set(None);
+        let _ = sender.send(failure);
+    });
+
+    match receiver.recv_timeout(TIMEOUT) {
+        Ok(None) => {}
+        Ok(Some((seed, error, calls))) => {
+            panic!("case {seed} failed, {error}, calls:\n{calls:#?}")
+        }
+        Err(_) => {
+            let seed = current_seed.load(Ordering::Relaxed);
+            panic!(
+                "case {seed} did not finish, calls:\n{:#?}",
+                generate(seed, extreme)
+            )
+        }
+    }
+}
+
+/// Builds the wasm module into the temporary directory of the tests, `None` when the wasm32
+/// target or node are not installed.
+fn wasm_module() -> Option<&'static Path> {
+    static MODULE: OnceLock<Option<PathBuf>> = OnceLock::new();
+    MODULE
+        .get_or_init(|| {
+            let sysroot = Command::new("rustc")
+                .args(["--print", "sysroot"])
+                .output()
+                .ok()?;
+            let sysroot = PathBuf::from(String::from_utf8(sysroot.stdout).ok()?.trim());
+            let has_target = sysroot.join("lib/rustlib/wasm32-unknown-unknown").is_dir();
+            let has_node = Command::new("node").arg("--version").output().is_ok();
+            if !has_target || !has_node {
+                eprintln!("skipped, the wasm32-unknown-unknown target or node is not installed");
+                return None;
+            }
+            let target_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("wasm");
+            let status = Command::new(env!("CARGO"))
+                .args(["build", "--release", "--lib"])
+                .args(["--target", "wasm32-unknown-unknown", "--target-dir"])
+                .arg(&target_dir)
+                .current_dir(env!("CARGO_MANIFEST_DIR"))
+                .status()
+                .expect("cargo runs");
+            assert!(status.success(), "building the wasm module failed");
+            Some(target_dir.join("wasm32-unknown-unknown/release/quick_start_simple.wasm"))
+        })
+        .as_deref()
+}
+
+/// Plays the cases on a fresh instance of the wasm module each, see [`WASM_DRIVER`].
+fn play_wasm(
+    module: &Path,
+    name: &str,
+    cases: &[(u32, Vec<Call>)],
+    extra_pages: Option<u32>,
+) -> Output {
+    let dir = env::temp_dir();
+    let driver = dir.join(format!("ffi_fuzz_{}_{name}.cjs", std::process::id()));
+    let cases_path = dir.join(format!("ffi_fuzz_{}_{name}.txt", std::process::id()));
+    fs::write(&driver, WASM_DRIVER).expect("temp dir is writable");
+    let mut text = String::new();
+    for (seed, calls) in cases {
+        text += &format!("case {seed}\n");
+        for call in calls {
+            text += &format!("{call:?}\n");
+        }
+    }
+    fs::write(&cases_path, text).expect("temp dir is writable");
+    let output = Command::new("node")
+        .arg(&driver)
+        .arg(module)
+        .arg(&cases_path)
+        .args(extra_pages.map(|pages| pages.to_string()))
+        .output()
+        .expect("node runs");
+    let _ = fs::remove_file(&driver);
+    let _ = fs::remove_file(&cases_path);
+    output
+}
+
+#[test]
+fn extreme_integers_are_handled() {
+    fuzz(false);
+}
+
+#[test]
+fn extreme_floats_are_handled() {
+    fuzz(true);
+}
+
+#[test]
+fn extreme_values_do_not_trap_the_wasm_module() {
+    let Some(module) = wasm_module() else {
+        return;
+    };
+    let cases: Vec<_> = (0..CASES)
+        .flat_map(|seed| [(seed, generate(seed, false)), (seed, generate(seed, true))])
+        .collect();
+    let output = play_wasm(module, "extreme", &cases, None);
+    assert!(
+        output.status.success(),
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+/// Forces a panic in the wasm module by letting it run out of memory, the panic handler logs it
+/// through `debug_log` before the instance traps.
+#[test]
+fn panic_in_the_wasm_module_is_logged_before_it_traps() {
+    let Some(module) = wasm_module() else {
+        return;
+    };
+    let mut calls = vec![Call::ClearWorldState];
+    calls.extend((0..100_000).map(|agent_id| Call::UpdateShip(agent_id, 3, 0.5, 0.5, 0.0)));
+    let output = play_wasm(module, "panic", &[(0, calls)], Some(1));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!output.status.success(), "{stdout}");
+    assert!(stdout.contains("trapped with unreachable"), "{stdout}");
+    assert!(stdout.contains("memory allocation of"), "{stdout}");
+}
@@ -0,0 +1,710 @@
+//! Scripted scenarios for testing the decisions of the agent. A scenario describes the config,
+//! the ships and shots of one or more ticks and what the actions of our agents on those ticks
+//! must look like. It is played through the exported functions, like the host does.
+//!
+//! ```text
+//! # comments start with '#'
+//! [scenario]
+//! own = 0, 1
+//! seed = 7
+//!
+//! [config]
+//! ship_max_turn_rate = 10
+//! ship_max_velocity = 0.005
+//! ship_hit_radius = 0.02
+//! shot_velocity = 0.01
+//! shot_lifetime = 20
+//!
+//! [tick 1]
+//! ship 0 hp=3 x=0.5 y=0.5 heading=90
+//! ship 2 hp=3 x=0.7 y=0.5 heading=270
+//! shot 2 lifetime=10 x=0.6 y=0.5 heading=270
+//! expect 0 no fire
+//! expect 0 turn away from shot 2
+//! ```
+//!
+//! `agents` (passed to `init_agent`, defaults to the highest agent id plus one) and `seed`
+//! (defaults to 0) are optional, `own` lists the agents whose actions are requested on every
+//! tick. `modes` optionally limits the scenario to some decision modes (`behavior-tree`,
+//! `planner`), it is skipped when the agent was built with another one. `budget` sets the work units of every decision and `safe_mode = on` starts the agent in
+//! safe mode. Headings are in degrees like the host sends them. Expectations are
+//! `fire`, `no fire`, `thrust`, `no thrust`, `turn left`, `turn right`, `no turn`,
+//! `turn away from ship|shot <agent>`, `turn toward ship|shot <agent>`, `action <flags>`
+//! for the exact action, with the flags `thrust`, `left`, `right` and `fire` or `none`, and
+//! `safe mode` or `no safe mode` for whether the action was decided in safe mode, `mode <mode>`
+//! for the mode of the ship afterwards and `mode <from> -> <to>` for the transition on the tick,
+//! with the modes `idle`, `hunting`, `aiming`, `firing`, `reloading`, `evading` and
+//! `retreating`.
+//!
+//! A `[ticks A-B]` section repeats its ships, shots and expectations on every tick from `A` to
+//! `B`. A field of a ship or shot written as `start+stept` or `start-stept` changes by `step`
+//! on every tick of the range, e.g. `x=0.9-0.005t` moves a ship by 0.005 to the left per tick.
+
+use std::fmt::{Display, Formatter};
+
+use quick_start_simple::{
+    Context, DecisionMode, ShipMode, clear_world_state, free_context, init_agent, make_action,
+    set_config_parameter, update_ship, update_shot,
+};
+
+/// Types and constants of the enums in `scubywasm_agent.h`, generated by `build.rs`.
+#[allow(non_upper_case_globals, dead_code)]
+mod bindings {
+    include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
+}
+
+/// Names of the flags of an action, in the order in which they are displayed.
+const FLAG_NAMES: [(u32, &str); 4] = [
+    (bindings::ActionFlags_ACTION_THRUST, "thrust"),
+    (bindings::ActionFlags_ACTION_TURN_LEFT, "left"),
+    (bindings::ActionFlags_ACTION_TURN_RIGHT, "right"),
+    (bindings::ActionFlags_ACTION_FIRE, "fire"),
+];
+
+/// Names of the decision modes as they are written in `modes`.
+const MODE_NAMES: [(DecisionMode, &str); 2] = [
+    (DecisionMode::BehaviorTree, "behavior-tree"),
+    (DecisionMode::Planner, "planner"),
+];
+
+/// Names of the config parameters as they are written in the `[config]` section.
+const CONFIG_NAMES: [(bindings::ConfigParameter, &str); 5] = [
+    (
+        bindings::ConfigParameter_CFG_SHIP_MAX_TURN_RATE,
+        "ship_max_turn_rate",
+    ),
+    (
+        bindings::ConfigParameter_CFG_SHIP_MAX_VELOCITY,
+        "ship_max_velocity",
+    ),
+    (
+        bindings::ConfigParameter_CFG_SHIP_HIT_RADIUS,
+        "ship_hit_radius",
+    ),
+    (bindings::ConfigParameter_CFG_SHOT_VELOCITY, "shot_velocity"),
+    (bindings::ConfigParameter_CFG_SHOT_LIFETIME, "shot_lifetime"),
+];
+
+pub struct Scenario {
+    pub name: String,
+    pub agents: u32,
+    pub seed: u32,
+    /// agents whose actions are requested, in this order
+    pub own: Vec<u32>,
+    /// decision modes the scenario applies to, all when empty
+    pub modes: Vec<String>,
+    /// work units of every decision, the default budget when `None`
+    pub budget: Option<u32>,
+    /// whether the agent starts in safe mode
+    pub safe_mode: bool,
+    pub config: Vec<(bindings::ConfigParameter, f32)>,
+    pub ticks: Vec<Tick>,
+}
+
+/// World state of one tick and the expectations on the actions of that tick.
+pub struct Tick {
+    pub tick: u32,
+    /// line of the `[tick N]` header
+    pub line: usize,
+    pub ships: Vec<ShipPlacement>,
+    pub shots: Vec<ShotPlacement>,
+    pub expectations: Vec<Expectation>,
+}
+
+pub struct ShipPlacement {
+    pub agent_id: u32,
+    pub hp: i32,
+    pub pos_x: f32,
+    pub pos_y: f32,
+    /// in degrees, as sent by the host
+    pub heading: f32,
+}
+
+pub struct ShotPlacement {
+    pub agent_id: u32,
+    pub lifetime: i32,
+    pub pos_x: f32,
+    pub pos_y: f32,
+    /// in degrees, as sent by the host
+    pub heading: f32,
+}
+
+pub struct Expectation {
+    pub agent_id: u32,
+    pub predicate: Predicate,
+    /// line of the expectation in the scenario
+    pub line: usize,
+}
+
+/// What the action of an agent must look like.
+pub enum Predicate {
+    Fire(bool),
+    Thrust(bool),
+    /// turn in the direction, `None` for not turning
+    Turn(Option<u32>),
+    /// turn away from (`false`) or toward (`true`) the object
+    TurnRelative {
+        toward: bool,
+        object: Object,
+    },
+    /// exactly this action
+    Action(u32),
+    /// decided in safe mode (`true`) or by the main strategy (`false`)
+    SafeMode(bool),
+    /// mode of the ship after the decision
+    Mode(ShipMode),
+    /// transition of the ship from one mode to the other on this tick
+    Transition(ShipMode, ShipMode),
+}
+
+/// Ship or shot of an agent, each agent has at most one of each.
+#[derive(Clone, Copy)]
+pub enum Object {
+    Ship(u32),
+    Shot(u32),
+}
+
+#[derive(Debug)]
+pub struct ParseError {
+    pub line: usize,
+    pub message: String,
+}
+
+/// Expectation that the action did not fulfill.
+pub struct Mismatch {
+    pub scenario: String,
+    pub tick: u32,
+    pub agent_id: u32,
+    pub line: usize,
+    pub expected: String,
+    pub action: u32,
+}
+
+impl Scenario {
+    /// Parses the scenario `text`, `name` is used in the reported mismatches.
+    pub fn parse(name: &str, text: &str) -> Result<Scenario, ParseError> {
+        let mut scenario = Scenario {
+            name: name.to_string(),
+            agents: 0,
+            seed: 0,
+            own: Vec::new(),
+            modes: Vec::new(),
+            budget: None,
+            safe_mode: false,
+            config: Vec::new(),
+            ticks: Vec::new(),
+        };
+        let mut agents = None;
+        let mut section = "";
+        // ticks of the current tick section, one unless it is a range
+        let mut range_ticks = 1;
+        for (index, line) in text.lines().enumerate() {
+            let line_number = index + 1;
+            let error = |message: String| ParseError {
+                line: line_number,
+                message,
+            };
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(header) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                let header = header.trim();
+                let range = if let Some(tick) = header.strip_prefix("tick ") {
+                    let tick = parse_number(tick.trim()).map_err(error)?;
+                    Some((tick, tick))
+                } else if let Some(range) = header.strip_prefix("ticks ") {
+                    let (first, last) = range
+                        .split_once('-')
+                        .ok_or_else(|| error(format!("expected `ticks A-B`, got `{header}`")))?;
+                    let first: u32 = parse_number(first.trim()).map_err(error)?;
+                    let last = parse_number(last.trim()).map_err(error)?;
+                    if last < first {
+                        return Err(error(format!("tick range {range} is empty")));
+                    }
+                    Some((first, last))
+                } else {
+                    None
+                };
+                if let Some((first, last)) = range {
+                    if scenario.ticks.last().is_some_and(|tick| tick.tick >= first) {
+                        return Err(error(format!("tick {first} does not follow the last tick")));
+                    }
+                    scenario.ticks.extend((first..=last).map(|tick| Tick {
+                        tick,
+                        line: line_number,
+                        ships: Vec::new(),
+                        shots: Vec::new(),
+                        expectations: Vec::new(),
+                    }));
+                    range_ticks = last - first + 1;
+                    section = "tick";
+                } else if header == "scenario" || header == "config" {
+                    section = header;
+                } else {
+                    return Err(error(format!("unknown section [{header}]")));
+                }
+                continue;
+            }
+            match section {
+                "scenario" | "config" => {
+                    let (key, value) = line
+                        .split_once('=')
+                        .ok_or_else(|| error(format!("expected `key = value`, got `{line}`")))?;
+                    let (key, value) = (key.trim(), value.trim());
+                    match (section, key) {
+                        ("scenario", "agents") => {
+                            agents = Some(parse_number(value).map_err(error)?)
+                        }
+                        ("scenario", "seed") => {
+                            scenario.seed = parse_number(value).map_err(error)?
+                        }
+                        ("scenario", "own") => {
+                            scenario.own = value
+                                .split(',')
+                                .map(|id| parse_number(id.trim()))
+                                .collect::<Result<_, _>>()
+                                .map_err(error)?;
+                        }
+                        ("scenario", "modes") => {
+                            for mode in value.split(',').map(str::trim) {
+                                if !MODE_NAMES.iter().any(|(_, name)| *name == mode) {
+                                    return Err(error(format!("unknown decision mode `{mode}`")));
+                                }
+                                scenario.modes.push(mode.to_string());
+                            }
+                        }
+                        ("scenario", "budget") => {
+                            scenario.budget = Some(parse_number(value).map_err(error)?)
+                        }
+                        ("scenario", "safe_mode") => {
+                            scenario.safe_mode = match value {
+                                "on" => true,
+                                "off" => false,
+                                _ => {
+                                    return Err(error(format!(
+                                        "expected `on` or `off`, got `{value}`"
+                                    )));
+                                }
+                            }
+                        }
+                        ("config", key) => {
+                            let (param, _) = CONFIG_NAMES
+                                .iter()
+                                .find(|(_, name)| *name == key)
+                                .ok_or_else(|| {
+                                    error(format!("unknown config parameter `{key}`"))
+                                })?;
+                            scenario
+                                .config
+                                .push((*param, parse_number(value).map_err(error)?));
+                        }
+                        _ => return Err(error(format!("unknown key `{key}`"))),
+                    }
+                }
+                "tick" => {
+                    let start = scenario.ticks.len() - range_ticks as usize;
+                    for (offset, tick) in (0..).zip(&mut scenario.ticks[start..]) {
+                        parse_statement(tick, line, line_number, offset).map_err(error)?;
+                    }
+                }
+                _ => return Err(error("expected a section before the first entry".into())),
+            }
+        }
+
+        if scenario.own.is_empty() {
+            return Err(ParseError {
+                line: 0,
+                message: "no own agents, set `own` in [scenario]".into(),
+            });
+        }
+        let highest_agent_id = scenario
+            .ticks
+            .iter()
+            .flat_map(|tick| tick.ships.iter().map(|ship| ship.agent_id))
+            .chain(scenario.own.iter().copied())
+            .max()
+            .unwrap_or_default();
+        scenario.agents = agents.unwrap_or(highest_agent_id + 1);
+        Ok(scenario)
+    }
+
+    /// Plays the scenario through the exported functions and returns the expectations that the
+    /// actions did not fulfill. A panic caught while deciding is reported as well.
+    ///
+    /// Our agents are only known to the agent after their first action was requested, so the
+    /// actions of the own agents are requested once on an empty world before the first tick.
+    pub fn run(&self) -> Vec<Mismatch> {
+        let mut ctx = init_agent(self.agents, 1, self.seed);
+        let applies = MODE_NAMES.iter().any(|(mode, name)| {
+            *mode == ctx.decision_mode() && self.modes.iter().any(|listed| listed == name)
+        });
+        if !self.modes.is_empty() && !applies {
+            return Vec::new();
+        }
+        for &(param, value) in &self.config {
+            set_config_parameter(&mut ctx, param, value);
+        }
+        if let Some(budget) = self.budget {
+            ctx.set_compute_budget(budget);
+        }
+        let first_tick = self.ticks.first().map_or(0, |tick| tick.tick);
+        clear_world_state(&mut ctx);
+        for &agent_id in &self.own {
+            make_action(&mut ctx, agent_id, first_tick.saturating_sub(1));
+        }
+        if self.safe_mode {
+            ctx.enter_safe_mode(first_tick);
+        }
+
+        let mut mismatches = Vec::new();
+        let mut degraded = ctx.degraded();
+        for tick in &self.ticks {
+            clear_world_state(&mut ctx);
+            for ship in &tick.ships {
+                update_ship(
+                    &mut ctx,
+                    ship.agent_id,
+                    ship.hp,
+                    ship.pos_x,
+                    ship.pos_y,
+                    ship.heading,
+                );
+            }
+            for shot in &tick.shots {
+                update_shot(
+                    &mut ctx,
+                    shot.agent_id,
+                    shot.lifetime,
+                    shot.pos_x,
+                    shot.pos_y,
+                    shot.heading,
+                );
+            }
+            for &agent_id in &self.own {
+                let action = make_action(&mut ctx, agent_id, tick.tick);
+                if ctx.degraded() && !degraded {
+                    degraded = true;
+                    mismatches.push(Mismatch {
+                        scenario: self.name.clone(),
+                        tick: tick.tick,
+                        agent_id,
+                        line: tick.line,
+                        expected: "no panic".to_string(),
+                        action,
+                    });
+                }
+                for expectation in tick
+                    .expectations
+                    .iter()
+                    .filter(|expectation| expectation.agent_id == agent_id)
+                {
+                    if !expectation.predicate.holds(action, &ctx, agent_id, tick) {
+                        mismatches.push(Mismatch {
+                            scenario: self.name.clone(),
+                            tick: tick.tick,
+                            agent_id,
+                            line: expectation.line,
+                            expected: expectation.predicate.to_string(),
+                            action,
+                        });
+                    }
+                }
+            }
+        }
+        free_context(&mut ctx);
+        mismatches
+    }
+}
+
+impl Predicate {
+    /// Whether `action` of `agent_id` and the state `ctx` is left in afterwards fulfill the
+    /// predicate on `tick`.
+    fn holds(&self, action: u32, ctx: &Context, agent_id: u32, tick: &Tick) -> bool {
+        let turn = action
+            & (bindings::ActionFlags_ACTION_TURN_LEFT | bindings::ActionFlags_ACTION_TURN_RIGHT);
+        match *self {
+            Self::Fire(fire) => (action & bindings::ActionFlags_ACTION_FIRE != 0) == fire,
+            Self::Thrust(thrust) => (action & bindings::ActionFlags_ACTION_THRUST != 0) == thrust,
+            Self::Turn(direction) => turn == direction.unwrap_or(0),
+            Self::TurnRelative { toward, object } => {
+                let Some(own) = tick.ships.iter().find(|ship| ship.agent_id == agent_id) else {
+                    return false;
+                };
+                let Some(position) = object.position(tick) else {
+                    return false;
+                };
+                let (dx, dy) = wrapped_delta((own.pos_x, own.pos_y), position);
+                let heading = (90.0 - own.heading).to_radians();
+                // positive when the object is to the left of our heading
+                let side = heading.cos() * dy - heading.sin() * dx;
+                let turning_left = turn == bindings::ActionFlags_ACTION_TURN_LEFT;
+                let turning_right = turn == bindings::ActionFlags_ACTION_TURN_RIGHT;
+                if toward {
+                    (side > 0.0 && turning_left) || (side < 0.0 && turning_right)
+                } else if side == 0.0 {
+                    // straight ahead or behind, both directions turn away
+                    turning_left || turning_right
+                } else {
+                    (side > 0.0 && turning_right) || (side < 0.0 && turning_left)
+                }
+            }
+            Self::Action(expected) => action == expected,
+            Self::SafeMode(expected) => ctx.in_safe_mode() == expected,
+            Self::Mode(mode) => ctx.ship_mode(agent_id) == Some(mode),
+            Self::Transition(from, to) => ctx
+                .mode_transitions(agent_id)
+                .last()
+                .is_some_and(|last| last.tick == tick.tick && last.from == from && last.to == to),
+        }
+    }
+}
+
+impl Object {
+    fn position(self, tick: &Tick) -> Option<(f32, f32)> {
+        match self {
+            Self::Ship(agent_id) => tick
+                .ships
+                .iter()
+                .find(|ship| ship.agent_id == agent_id)
+                .map(|ship| (ship.pos_x, ship.pos_y)),
+            Self::Shot(agent_id) => tick
+                .shots
+                .iter()
+                .find(|shot| shot.agent_id == agent_id)
+                .map(|shot| (shot.pos_x, shot.pos_y)),
+        }
+    }
+}
+
+/// Parses a `ship`, `shot` or `expect` line of a tick section into `tick`, the `offset`-th tick
+/// of its section.
+fn parse_statement(
+    tick: &mut Tick,
+    line: &str,
+    line_number: usize,
+    offset: u32,
+) -> Result<(), String> {
+    let mut words = line.split_whitespace();
+    let kind = words.next().unwrap_or_default();
+    let agent_id = parse_number(words.next().ok_or("missing agent id")?)?;
+    match kind {
+        "ship" => {
+            let fields = Fields::parse(words, &["hp", "x", "y", "heading"], offset)?;
+            tick.ships.push(ShipPlacement {
+                agent_id,
+                hp: fields.get("hp")?,
+                pos_x: fields.get("x")?,
+                pos_y: fields.get("y")?,
+                heading: fields.get("heading")?,
+            });
+        }
+        "shot" => {
+            let fields = Fields::parse(words, &["lifetime", "x", "y", "heading"], offset)?;
+            tick.shots.push(ShotPlacement {
+                agent_id,
+                lifetime: fields.get("lifetime")?,
+                pos_x: fields.get("x")?,
+                pos_y: fields.get("y")?,
+                heading: fields.get("heading")?,
+            });
+        }
+        "expect" => {
+            let words: Vec<&str> = words.collect();
+            let object = |kind: &str, id: &str| -> Result<Object, String> {
+                let id = parse_number(id)?;
+                match kind {
+                    "ship" => Ok(Object::Ship(id)),
+                    "shot" => Ok(Object::Shot(id)),
+                    _ => Err(format!("expected `ship` or `shot`, got `{kind}`")),
+                }
+            };
+            let predicate = match words.as_slice() {
+                ["fire"] => Predicate::Fire(true),
+                ["no", "fire"] => Predicate::Fire(false),
+                ["thrust"] => Predicate::Thrust(true),
+                ["no", "thrust"] => Predicate::Thrust(false),
+                ["turn", "left"] => Predicate::Turn(Some(bindings::ActionFlags_ACTION_TURN_LEFT)),
+                ["turn", "right"] => Predicate::Turn(Some(bindings::ActionFlags_ACTION_TURN_RIGHT)),
+                ["no", "turn"] => Predicate::Turn(None),
+                ["turn", "away", "from", kind, id] => Predicate::TurnRelative {
+                    toward: false,
+                    object: object(kind, id)?,
+                },
+                ["turn", "toward", kind, id] => Predicate::TurnRelative {
+                    toward: true,
+                    object: object(kind, id)?,
+                },
+                ["mode", mode] => Predicate::Mode(parse_mode(mode)?),
+                ["mode", from, "->", to] => {
+                    Predicate::Transition(parse_mode(from)?, parse_mode(to)?)
+                }
+                ["safe", "mode"] => Predicate::SafeMode(true),
+                ["no", "safe", "mode"] => Predicate::SafeMode(false),
+                ["action", "none"] => Predicate::Action(bindings::ActionFlags_ACTION_NONE),
+                ["action", flags @ ..] if !flags.is_empty() => {
+                    let mut action = 0;
+                    for flag in flags {
+                        let (bit, _) = FLAG_NAMES
+                            .iter()
+                            .find(|(_, name)| name == flag)
+                            .ok_or_else(|| format!("unknown action flag `{flag}`"))?;
+                        action |= bit;
+                    }
+                    Predicate::Action(action)
+                }
+                _ => return Err(format!("unknown expectation `{}`", words.join(" "))),
+            };
+            tick.expectations.push(Expectation {
+                agent_id,
+                predicate,
+                line: line_number,
+            });
+        }
+        _ => return Err(format!("expected `ship`, `shot` or `expect`, got `{kind}`")),
+    }
+    Ok(())
+}
+
+/// `key=value` fields of a `ship` or `shot` line on the `offset`-th tick of its section.
+struct Fields<'a> {
+    fields: Vec<(&'a str, &'a str)>,
+    offset: u32,
+}
+
+impl<'a> Fields<'a> {
+    /// Parses the fields, all `keys` are required and no other keys are allowed.
+    fn parse(
+        words: impl Iterator<Item = &'a str>,
+        keys: &[&str],
+        offset: u32,
+    ) -> Result<Self, String> {
+        let mut fields = Vec::new();
+        for word in words {
+            let (key, value) = word
+                .split_once('=')
+                .ok_or_else(|| format!("expected `key=value`, got `{word}`"))?;
+            if !keys.contains(&key) {
+                return Err(format!("unknown field `{key}`"));
+            }
+            fields.push((key, value));
+        }
+        if let Some(missing) = keys
+            .iter()
+            .find(|key| !fields.iter().any(|(field, _)| field == *key))
+        {
+            return Err(format!("missing field `{missing}`"));
+        }
+        Ok(Self { fields, offset })
+    }
+
+    /// Value of the field `key`, a `start+stept` value is evaluated on the tick of the field.
+    fn get<T: std::str::FromStr>(&self, key: &str) -> Result<T, String> {
+        let (_, value) = self
+            .fields
+            .iter()
+            .find(|(field, _)| *field == key)
+            .ok_or_else(|| format!("missing field `{key}`"))?;
+        let Some(linear) = value.strip_suffix('t') else {
+            return parse_number(value);
+        };
+        // the sign of the step, a sign at the start belongs to `start`
+        let split = linear
+            .char_indices()
+            .skip(1)
+            .filter(|&(_, sign)| sign == '+' || sign == '-')
+            .last()
+            .map(|(index, _)| index)
+            .ok_or_else(|| format!("expected `start+stept`, got `{value}`"))?;
+        let start: f64 = parse_number(&linear[..split])?;
+        let step: f64 = parse_number(linear[split..].trim_start_matches('+'))?;
+        parse_number(&(start + step * f64::from(self.offset)).to_string())
+    }
+}
+
+/// Shortest offset from `from` to `to` on the playfield, which wraps around at 1.
+fn wrapped_delta(from: (f32, f32), to: (f32, f32)) -> (f32, f32) {
+    let wrap = |delta: f32| delta - delta.round();
+    (wrap(to.0 - from.0), wrap(to.1 - from.1))
+}
+
+fn parse_mode(name: &str) -> Result<ShipMode, String> {
+    ShipMode::ALL
+        .into_iter()
+        .find(|mode| mode.to_string() == name)
+        .ok_or_else(|| format!("unknown mode `{name}`"))
+}
+
+fn parse_number<T: std::str::FromStr>(text: &str) -> Result<T, String> {
+    text.parse()
+        .map_err(|_| format!("`{text}` is not a valid number"))
+}
+
+/// Displays the flags of an action, like `thrust left`.
+struct Flags(u32);
+
+impl Display for Flags {
+    fn fmt(&self, formatter: &mut Formatter) -> std::fmt::Result {
+        if self.0 == bindings::ActionFlags_ACTION_NONE {
+            return formatter.write_str("none");
+        }
+        let mut first = true;
+        for (bit, name) in FLAG_NAMES {
+            if self.0 & bit != 0 {
+                if !first {
+                    formatter.write_str(" ")?;
+                }
+                formatter.write_str(name)?;
+                first = false;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Display for Predicate {
+    fn fmt(&self, formatter: &mut Formatter) -> std::fmt::Result {
+        match *self {
+            Self::Fire(true) => write!(formatter, "fire"),
+            Self::Fire(false) => write!(formatter, "no fire"),
+            Self::Thrust(true) => write!(formatter, "thrust"),
+            Self::Thrust(false) => write!(formatter, "no thrust"),
+            Self::Turn(None) => write!(formatter, "no turn"),
+            Self::Turn(Some(direction)) => write!(formatter, "turn {}", Flags(direction)),
+            Self::TurnRelative { toward, object } => {
+                let (kind, agent_id) = match object {
+                    Object::Ship(agent_id) => ("ship", agent_id),
+                    Object::Shot(agent_id) => ("shot", agent_id),
+                };
+                let direction = if toward { "toward" } else { "away from" };
+                write!(formatter, "turn {direction} {kind} {agent_id}")
+            }
+            Self::Action(action) => write!(formatter, "action {}", Flags(action)),
+            Self::SafeMode(true) => write!(formatter, "safe mode"),
+            Self::SafeMode(false) => write!(formatter, "no safe mode"),
+            Self::Mode(mode) => write!(formatter, "mode {mode}"),
+            Self::Transition(from, to) => write!(formatter, "mode {from} -> {to}"),
+        }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, formatter: &mut Formatter) -> std::fmt::Result {
+        write!(formatter, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Display for Mismatch {
+    fn fmt(&self, formatter: &mut Formatter) -> std::fmt::Result {
+        write!(
+            formatter,
+            "{}:{}: tick {}, agent {}: expected {}, got action {}",
+            self.scenario,
+            self.line,
+            self.tick,
+            self.agent_id,
+            self.expected,
+            Flags(self.action)
+        )
+    }
+}
@@ -0,0 +1,91 @@
+//! Plays the scenarios in `tests/scenarios`, `tests/golden` and `tests/safe_mode` and reports
+//! the expectations that the actions did not fulfill, see `tests/scenario` for the format.
+
+mod scenario;
+
+use std::{fmt::Write, fs, path::Path};
+
+use scenario::Scenario;
+
+/// Plays every scenario in `directory` and fails with all unmet expectations.
+fn play_directory(directory: &str) {
+    let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join(directory);
+    let mut paths: Vec<_> = fs::read_dir(&directory)
+        .expect("scenario directory exists")
+        .map(|entry| entry.expect("scenario directory is readable").path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "scenario")
+        })
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty(), "no scenarios in {}", directory.display());
+
+    let mut failures = Vec::new();
+    for path in &paths {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let text = fs::read_to_string(path).expect("scenario is readable");
+        failures.extend(play(&name, &text));
+    }
+    assert_no_failures(&failures);
+}
+
+/// Plays the scenario `text` and returns its unmet expectations.
+fn play(name: &str, text: &str) -> Vec<String> {
+    match Scenario::parse(name, text) {
+        Ok(scenario) => scenario.run().iter().map(ToString::to_string).collect(),
+        Err(error) => vec![format!("{name}:{error}")],
+    }
+}
+
+fn assert_no_failures(failures: &[String]) {
+    assert!(
+        failures.is_empty(),
+        "{} of the expectations failed:\n{}",
+        failures.len(),
+        failures.join("\n")
+    );
+}
+
+#[test]
+fn scenarios_hold() {
+    play_directory("tests/scenarios");
+}
+
+/// The behavior described by every changelog entry, as exact actions in concrete world states.
+#[test]
+fn changelog_contracts_hold() {
+    play_directory("tests/golden");
+}
+
+/// The backup policy of safe mode and when it is entered and left, in every decision mode.
+#[test]
+fn safe_mode_holds() {
+    play_directory("tests/safe_mode");
+}
+
+/// A crowded world of 160 ships on a grid around ours: every decision uses up the budget but
+/// still decides, so safe mode is never entered.
+#[test]
+fn safe_mode_stays_off_in_crowded_world() {
+    const COLUMNS: u32 = 13;
+    const SPACING: f32 = 0.075;
+
+    let mut text = String::from(
+        "[scenario]\nown = 0\n\n[config]\nshot_velocity = 0.01\nshot_lifetime = 20\n\n\
+         [ticks 1-6]\nship 0 hp=3 x=0.5 y=0.5 heading=0\nexpect 0 no safe mode\n",
+    );
+    for agent_id in 1..=160 {
+        let column = (agent_id - 1) % COLUMNS;
+        let row = (agent_id - 1) / COLUMNS;
+        writeln!(
+            text,
+            "ship {agent_id} hp=3 x={} y={} heading={}",
+            0.02 + SPACING * column as f32,
+            0.02 + SPACING * row as f32,
+            agent_id * 37 % 360
+        )
+        .expect("writing to a string does not fail");
+    }
+    assert_no_failures(&play("crowded world", &text));
+}
@@ -0,0 +1,266 @@
+//! Runs the `wasm-audit` tool on small modules that are assembled by hand, since the tests can't
+//! build the agent for wasm32.
+
+use std::{
+    env, fs,
+    process::{Command, Output},
+};
+
+/// Name and wasm signature of every function in `scubywasm_agent.h`.
+const EXPORTS: &[(&str, &str)] = include!(concat!(env!("OUT_DIR"), "/wasm_exports.rs"));
+
+const DEBUG_LOG: Function = Function {
+    module: "debug",
+    name: "debug_log",
+    signature: "(i32, i32)",
+};
+
+struct Function {
+    /// module of an import, empty for exports
+    module: &'static str,
+    name: &'static str,
+    signature: &'static str,
+}
+
+/// Functions of the header, as exported by the agent.
+fn header_exports() -> Vec<Function> {
+    EXPORTS
+        .iter()
+        .map(|&(name, signature)| Function {
+            module: "",
+            name,
+            signature,
+        })
+        .collect()
+}
+
+/// Assembles a module that imports and exports the functions, every function gets its own type.
+fn module(imports: &[Function], exports: &[Function]) -> Vec<u8> {
+    let mut types = Vec::new();
+    for function in imports.iter().chain(exports) {
+        types.push(0x60);
+        let (parameters, results) = function
+            .signature
+            .split_once(" -> ")
+            .unwrap_or((function.signature, ""));
+        let parameters = parameters.trim_matches(['(', ')']);
+        for value_types in [parameters, results] {
+            let value_types: Vec<u8> = value_types
+                .split(", ")
+                .filter(|value_type| !value_type.is_empty())
+                .map(|value_type| match value_type {
+                    "i32" => 0x7f,
+                    "f32" => 0x7d,
+                    _ => panic!("unsupported value type {value_type}"),
+                })
+                .collect();
+            push_vector(&mut types, value_types.len(), &value_types);
+        }
+    }
+    let mut import_section = Vec::new();
+    for (index, import) in imports.iter().enumerate() {
+        push_name(&mut import_section, import.module);
+        push_name(&mut import_section, import.name);
+        import_section.extend([0, index as u8]);
+    }
+    let mut functions = Vec::new();
+    let mut export_section = Vec::new();
+    let mut code = Vec::new();
+    for (index, export) in exports.iter().enumerate() {
+        let function_index = (imports.len() + index) as u8;
+        functions.push(function_index);
+        push_name(&mut export_section, export.name);
+        export_section.extend([0, function_index]);
+        // no locals, unreachable, end
+        push_vector(&mut code, 3, &[0x00, 0x00, 0x0b]);
+    }
+
+    let mut module = b"\0asm\x01\0\0\0".to_vec();
+    let count = imports.len() + exports.len();
+    for (id, count, content) in [
+        (1, count, types),
+        (2, imports.len(), import_section),
+        (3, exports.len(), functions),
+        (7, exports.len(), export_section),
+        (10, exports.len(), code),
+    ] {
+        let mut section = vec![count as u8];
+        section.extend(content);
+        module.push(id);
+        push_vector(&mut module, section.len(), &section);
+    }
+    module
+}
+
+/// Appends a `name` section that names the functions with `names`, by function index.
+fn with_names(mut module: Vec<u8>, names: &[(u8, &str)]) -> Vec<u8> {
+    let mut function_names = vec![names.len() as u8];
+    for &(index, name) in names {
+        function_names.push(index);
+        push_name(&mut function_names, name);
+    }
+    let mut section = Vec::new();
+    push_name(&mut section, "name");
+    // subsection 1 names the functions
+    section.push(1);
+    push_vector(&mut section, function_names.len(), &function_names);
+    module.push(0);
+    push_vector(&mut module, section.len(), &section);
+    module
+}
+
+/// Pushes the length as LEB128 and the bytes.
+fn push_vector(bytes: &mut Vec<u8>, length: usize, content: &[u8]) {
+    let mut length = length;
+    loop {
+        let byte = (length & 0x7f) as u8;
+        length >>= 7;
+        if length == 0 {
+            bytes.push(byte);
+            break;
+        }
+        bytes.push(byte | 0x80);
+    }
+    bytes.extend(content);
+}
+
+fn push_name(bytes: &mut Vec<u8>, name: &str) {
+    push_vector(bytes, name.len(), name.as_bytes());
+}
+
+/// Writes the module to a temporary file and audits it.
+fn audit(name: &str, module: &[u8]) -> Output {
+    let path = env::temp_dir().join(format!("wasm_audit_{}_{name}.wasm", std::process::id()));
+    fs::write(&path, module).expect("temp dir is writable");
+    let output = Command::new(env!("CARGO_BIN_EXE_wasm-audit"))
+        .arg(&path)
+        .output()
+        .expect("wasm-audit runs");
+    let _ = fs::remove_file(&path);
+    output
+}
+
+#[test]
+fn module_of_the_header_passes() {
+    let output = audit("header", &module(&[DEBUG_LOG], &header_exports()));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        output.status.success(),
+        "{stdout}{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(stdout.contains("function make_action (i32, i32, i32) -> i32"));
+    assert!(stdout.contains("function debug.debug_log (i32, i32)"));
+    assert!(stdout.contains(&format!("code: {} bytes", 3 * EXPORTS.len())));
+}
+
+#[test]
+fn missing_and_unexpected_functions_fail() {
+    let mut exports = header_exports();
+    exports.retain(|export| export.name != "make_action");
+    exports.push(Function {
+        module: "",
+        name: "helper",
+        signature: "()",
+    });
+    let abort = Function {
+        module: "env",
+        name: "abort",
+        signature: "()",
+    };
+    let output = audit("unexpected", &module(&[abort], &exports));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success());
+    for problem in [
+        "missing export `make_action`",
+        "unexpected export `helper`",
+        "missing import `debug.debug_log`",
+        "unexpected import `env.abort`",
+    ] {
+        assert!(stderr.contains(problem), "{problem} not in:\n{stderr}");
+    }
+}
+
+#[test]
+fn signature_mismatch_fails() {
+    let mut exports = header_exports();
+    for export in &mut exports {
+        if export.name == "set_config_parameter" {
+            export.signature = "(i32, i32, i32)";
+        }
+    }
+    let output = audit("signature", &module(&[DEBUG_LOG], &exports));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success());
+    assert!(
+        stderr.contains(
+            "export `set_config_parameter` has the signature (i32, i32, i32), the header declares (i32, i32, f32)"
+        ),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn mangled_names_are_demangled_and_formatting_is_counted() {
+    // v0 names of the release build and a legacy name, by the index of an exported function
+    let names = [
+        (
+            1,
+            "_RNvNtNtNtNtCsgXGp5Oqx2Ny_4core3num7flt2dec8strategy6dragon15format_shortest",
+            "core::num::flt2dec::strategy::dragon::format_shortest",
+        ),
+        (
+            2,
+            "_RNvXsd_NtNtNtCsgXGp5Oqx2Ny_4core3fmt3num3impyNtB9_7Display3fmt",
+            "<u64 as core::fmt::Display>::fmt",
+        ),
+        (
+            3,
+            "_RINvNtNtCsgXGp5Oqx2Ny_4core3fmt5float29float_to_decimal_common_exactfEB6_",
+            "core::fmt::float::float_to_decimal_common_exact::<f32>",
+        ),
+        (
+            4,
+            "_RNvXs1g_NtCsgXGp5Oqx2Ny_4core3fmtRDNtB6_5DebugEL_Bx_3fmtB8_",
+            "<&dyn core::fmt::Debug as core::fmt::Debug>::fmt",
+        ),
+        (
+            5,
+            "_RNvNvMs_NtCs5cOc02OMXlo_5alloc3vecINtB6_3VecppE6remove13assert_failed",
+            "<alloc::vec::Vec<_, _>>::remove::assert_failed",
+        ),
+        (
+            6,
+            "_RNvNtCsgXGp5Oqx2Ny_4core9panicking9panic_fmt",
+            "core::panicking::panic_fmt",
+        ),
+        (
+            7,
+            "_ZN4core3fmt5write17h0123456789abcdefE",
+            "core::fmt::write",
+        ),
+        // malformed names are kept
+        (8, "_RNvB9_4core", "_RNvB9_4core"),
+    ];
+    let module = with_names(
+        module(&[DEBUG_LOG], &header_exports()),
+        &names.map(|(index, mangled, _)| (index, mangled)),
+    );
+    let output = audit("names", &module);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{stdout}");
+    for (_, _, demangled) in names {
+        assert!(
+            stdout.contains(&format!("3  {demangled}\n")),
+            "{demangled} not in:\n{stdout}"
+        );
+    }
+    assert!(
+        stdout.contains("formatting: 15 bytes in 5 functions"),
+        "{stdout}"
+    );
+    assert!(
+        stdout.contains("panicking: 3 bytes in 1 functions"),
+        "{stdout}"
+    );
+}